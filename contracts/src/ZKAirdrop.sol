@@ -1,26 +1,62 @@
 // SPDX-License-Identifier: MIT
 pragma solidity ^0.8.20;
 
+import {IERC20} from "./interfaces/IERC20.sol";
 import {IRiscZeroVerifier} from "./interfaces/IRiscZeroVerifier.sol";
 
 /// @title ZKAirdrop
 /// @notice Privacy-preserving token airdrop using RISC Zero zkVM
 /// @dev Users prove eligibility without revealing their address on-chain
 contract ZKAirdrop {
+    /// @notice Length of the fixed part of the claim output
+    uint256 internal constant CLAIM_HEAD_LENGTH = 454;
+    
+    /// @notice Length of one basket entry: token (20 bytes) + amount (32 bytes)
+    uint256 internal constant BASKET_ENTRY_LENGTH = 52;
+    
+    /// @notice Length of a tier claim output
+    uint256 internal constant TIER_OUTPUT_LENGTH = 267;
+    
+    /// @notice Length of the fixed part of a batch claim output
+    uint256 internal constant BATCH_HEAD_LENGTH = 166;
+    
+    /// @notice Length of one batch entry: nullifier (32 bytes) + recipient (20 bytes) + amount (32 bytes)
+    uint256 internal constant BATCH_ENTRY_LENGTH = 84;
+    
     /// @notice RISC Zero verifier contract
     IRiscZeroVerifier public immutable VERIFIER;
     
     /// @notice Image ID of the zkVM guest program
     bytes32 public immutable IMAGE_ID;
     
+    /// @notice Campaign identifier bound into every proof
+    bytes32 public immutable CAMPAIGN_ID;
+    
+    /// @notice Whether claims must prove control of the eligible address
+    bool public immutable REQUIRE_OWNERSHIP;
+    
+    /// @notice Merkle hash scheme the roots are built with (0 = SHA-256, 1 = Keccak-256, 2 = Poseidon, 3 = OpenZeppelin standard)
+    uint8 public immutable HASH_SCHEME;
+    
+    /// @notice Minimum snapshot balance for threshold campaigns (0 = allocation campaign)
+    uint256 public immutable MIN_BALANCE;
+    
+    /// @notice Fixed payout per claim in threshold campaigns
+    uint256 public immutable THRESHOLD_REWARD;
+    
     /// @notice Current airdrop epoch
     uint64 public currentEpoch;
     
     /// @notice Merkle root for the current epoch
     bytes32 public merkleRoot;
     
-    /// @notice Reward amount per claim
-    uint256 public rewardAmount;
+    /// @notice Accepted root sets: keccak256(abi.encodePacked(roots)) => accepted
+    /// @dev Claimers prove membership in one of the roots without revealing which
+    mapping(bytes32 => bool) public rootSets;
+    
+    /// @notice Roots replaced by rotateRoot => epoch they were replaced in
+    /// @dev Proofs against a retired root are accepted until the epoch changes
+    mapping(bytes32 => uint64) public retiredRoots;
     
     /// @notice Contract owner
     address public owner;
@@ -32,6 +68,29 @@ contract ZKAirdrop {
     /// @dev nullifier => claimed status
     mapping(bytes32 => bool) public nullifiers;
     
+    /// @notice Vesting tranches opened by the claim before them
+    /// @dev tranche nullifier => open status; tranche chains do not depend on the epoch
+    mapping(bytes32 => bool) public openTranches;
+    
+    /// @notice keccak256(abi.encodePacked(lowerBounds)) of the tier table (0 = no tiers)
+    bytes32 public tierTableDigest;
+    
+    /// @notice Payout per tier, indexed by the proven tier
+    uint256[] public tierRewards;
+    
+    /// @notice Root of the sparse exclusion tree claimers prove not to be in (0 = no exclusions)
+    bytes32 public exclusionRoot;
+    
+    /// @notice Commitment to the additional lists claimers must be on (0 = no policy)
+    /// @dev keccak256(abi.encodePacked(threshold, roots)); see setListPolicy
+    bytes32 public listPolicy;
+    
+    /// @notice Image ID of the batch claim guest program (0 = batch claims disabled)
+    bytes32 public batchImageId;
+    
+    /// @notice Image ID of the guest aggregating claim receipts (0 = aggregation disabled)
+    bytes32 public aggregateImageId;
+    
     /// @notice Emitted when a successful claim is made
     event Claimed(
         bytes32 indexed nullifier,
@@ -40,21 +99,59 @@ contract ZKAirdrop {
         uint64 epoch
     );
     
+    /// @notice Emitted when a relayer is paid its committed fee out of a claim
+    event RelayerPaid(bytes32 indexed nullifier, address indexed relayer, uint256 fee);
+    
     /// @notice Emitted when epoch is updated
     event EpochUpdated(uint64 indexed epoch, bytes32 merkleRoot);
     
+    /// @notice Emitted when the root is replaced within an epoch
+    event RootRotated(uint64 indexed epoch, bytes32 previousRoot, bytes32 merkleRoot);
+    
+    /// @notice Emitted when a root set is accepted or revoked
+    event RootSetUpdated(bytes32 indexed rootSet, bool accepted);
+    
+    /// @notice Emitted when the tier table or tier rewards change
+    event TiersUpdated(bytes32 tierTableDigest, uint256[] tierRewards);
+    
+    /// @notice Emitted when the exclusion list changes
+    event ExclusionRootUpdated(bytes32 exclusionRoot);
+    
+    /// @notice Emitted when the list policy changes
+    event ListPolicyUpdated(bytes32 listPolicy, bytes32[] roots, uint256 threshold);
+    
+    /// @notice Emitted when the batch guest image changes
+    event BatchImageIdUpdated(bytes32 batchImageId);
+    
+    /// @notice Emitted when the aggregation guest image changes
+    event AggregateImageIdUpdated(bytes32 aggregateImageId);
+    
     /// @notice Emitted when contract is paused/unpaused
     event PauseToggled(bool paused);
     
-    /// @notice Emitted when reward amount is updated
-    event RewardAmountUpdated(uint256 newAmount);
-    
     error InvalidProof();
     error AlreadyClaimed();
     error Paused();
     error Unauthorized();
     error InvalidMerkleRoot();
     error InvalidEpoch();
+    error InvalidCampaign();
+    error OwnershipNotProven();
+    error InvalidHashScheme();
+    error InvalidTimestamp();
+    error InvalidThreshold();
+    error TrancheNotOpen();
+    error InvalidTierTable();
+    error InvalidTier();
+    error TierClaimRequired();
+    error InvalidExclusionRoot();
+    error InvalidListPolicy();
+    error BatchClaimsDisabled();
+    error InvalidImageId();
+    error InvalidFee();
+    error InvalidDomain();
+    error ClaimNotYetValid();
+    error ClaimExpired();
     error TransferFailed();
     
     modifier onlyOwner() {
@@ -71,19 +168,31 @@ contract ZKAirdrop {
     /// @param _verifier Address of RISC Zero verifier contract
     /// @param _imageId Image ID of the zkVM guest program
     /// @param _merkleRoot Initial Merkle root
-    /// @param _rewardAmount Reward amount per claim
+    /// @param _campaignId Campaign identifier proofs must commit to
+    /// @param _requireOwnership Require an in-guest signature from the eligible address
+    /// @param _hashScheme Merkle hash scheme the roots are built with
+    /// @param _minBalance Balance threshold claimers must prove, or 0 to pay leaf allocations
+    /// @param _thresholdReward Payout per claim when `_minBalance` is set
     constructor(
         address _verifier,
         bytes32 _imageId,
         bytes32 _merkleRoot,
-        uint256 _rewardAmount
+        bytes32 _campaignId,
+        bool _requireOwnership,
+        uint8 _hashScheme,
+        uint256 _minBalance,
+        uint256 _thresholdReward
     ) {
         if (_merkleRoot == bytes32(0)) revert InvalidMerkleRoot();
         
         VERIFIER = IRiscZeroVerifier(_verifier);
         IMAGE_ID = _imageId;
+        CAMPAIGN_ID = _campaignId;
+        REQUIRE_OWNERSHIP = _requireOwnership;
+        HASH_SCHEME = _hashScheme;
+        MIN_BALANCE = _minBalance;
+        THRESHOLD_REWARD = _thresholdReward;
         merkleRoot = _merkleRoot;
-        rewardAmount = _rewardAmount;
         owner = msg.sender;
         currentEpoch = 1;
         
@@ -94,56 +203,223 @@ contract ZKAirdrop {
     /// @param seal The RISC Zero proof
     /// @param claimOutput The claim output from the guest program
     /// @dev claimOutput contains: merkleRoot (32 bytes) + nullifier (32 bytes) + epochId (8 bytes)
+    ///      + recipient (20 bytes) + campaignId (32 bytes) + ownershipVerified (1 byte)
+    ///      + hashScheme (1 byte) + amount (32 bytes) + basketDigest (32 bytes)
+    ///      + timestamp (8 bytes) + claimedAmount (32 bytes) + nextNullifier (32 bytes)
+    ///      + minBalance (32 bytes) + exclusionRoot (32 bytes) + policyCommitment (32 bytes)
+    ///      + relayer (20 bytes) + fee (32 bytes) + chainId (8 bytes) + verifyingContract (20 bytes)
+    ///      + notBefore (8 bytes) + expiresAt (8 bytes), followed by token (20 bytes) + amount (32 bytes) for each basket entry.
+    ///      The amount and basket are the allocation committed in the claimer's leaf. They go
+    ///      to the proven recipient, not msg.sender, so a copied seal and journal cannot
+    ///      redirect the payout. For vesting allocations the amount is what vested since the
+    ///      previous tranche, and the nullifier is that tranche's nullifier. Threshold campaigns
+    ///      pay THRESHOLD_REWARD; the proven balance itself is never revealed. The committed fee
+    ///      is paid out of the payout to the committed relayer, or to whoever submits the
+    ///      claim if no relayer was committed. The chain ID and verifying contract bind the
+    ///      proof, and its nullifier, to this deployment. A nonzero notBefore or expiresAt
+    ///      limits when the claim can be submitted. Once tiers are set, rewards are only
+    ///      paid through claimTier.
     function claim(bytes calldata seal, bytes calldata claimOutput) 
         external 
         whenNotPaused 
     {
-        // Decode claim output (72 bytes total: 32 + 32 + 8)
-        require(claimOutput.length == 72, "Invalid claim output length");
+        // Decode claim output (454 bytes: 32 + 32 + 8 + 20 + 32 + 1 + 1 + 32 + 32 + 8 + 32 + 32
+        // + 32 + 32 + 32 + 20 + 32 + 8 + 20 + 8 + 8, then 52 per token)
+        require(
+            claimOutput.length >= CLAIM_HEAD_LENGTH
+                && (claimOutput.length - CLAIM_HEAD_LENGTH) % BASKET_ENTRY_LENGTH == 0,
+            "Invalid claim output length"
+        );
         
-        bytes32 proofMerkleRoot;
-        bytes32 nullifier;
-        uint64 epochId;
+        (bytes32 nullifier, address recipient, uint64 epochId) = _checkHead(claimOutput);
+        _checkLists(claimOutput, 294);
+        _checkDomain(claimOutput, 410);
+        _checkWindow(claimOutput, 438);
         
-        assembly {
-            // Load merkleRoot (first 32 bytes)
-            proofMerkleRoot := calldataload(claimOutput.offset)
-            // Load nullifier (next 32 bytes)
-            nullifier := calldataload(add(claimOutput.offset, 32))
-            // Load epochId (last 8 bytes)
-            // calldataload loads 32 bytes, but we only want the first 8 bytes as uint64
-            let epochData := calldataload(add(claimOutput.offset, 64))
-            // Shift right to get the uint64 from the left-most 8 bytes
-            epochId := shr(192, epochData)
+        // Check the vesting timestamp and that later tranches were opened
+        _checkTranche(claimOutput, nullifier);
+        
+        // Work out the payout for this campaign type
+        uint256 amount = _claimAmount(claimOutput);
+        
+        // Verify the proof
+        bytes32 journalDigest = sha256(claimOutput);
+        try VERIFIER.verify(seal, IMAGE_ID, journalDigest) {
+            // Mark nullifier as used
+            nullifiers[nullifier] = true;
+            _openNextTranche(claimOutput, nullifier);
+            
+            // Transfer the committed allocation, less the relayer fee
+            uint256 fee = _payRelayer(claimOutput, nullifier, amount);
+            (bool success,) = recipient.call{value: amount - fee}("");
+            if (!success) revert TransferFailed();
+            
+            // Transfer each token of the basket
+            _payBasket(recipient, claimOutput);
+            
+            emit Claimed(nullifier, recipient, amount - fee, epochId);
+        } catch {
+            revert InvalidProof();
         }
+    }
+    
+    /// @notice Claim the reward of a proven tier
+    /// @param seal The RISC Zero proof
+    /// @param tierOutput The tier claim output from the guest program
+    /// @dev tierOutput contains the same first 126 bytes as a claim output, followed by
+    ///      tierTableDigest (32 bytes) + tier (1 byte) + exclusionRoot (32 bytes)
+    ///      + policyCommitment (32 bytes) + chainId (8 bytes) + verifyingContract (20 bytes)
+    ///      + notBefore (8 bytes) + expiresAt (8 bytes). The claimer's score stays private; only the tier it falls in is revealed and paid
+    ///      from tierRewards.
+    function claimTier(bytes calldata seal, bytes calldata tierOutput)
+        external
+        whenNotPaused
+    {
+        require(tierOutput.length == TIER_OUTPUT_LENGTH, "Invalid tier output length");
         
-        // Verify epoch matches
-        if (epochId != currentEpoch) revert InvalidEpoch();
+        (bytes32 nullifier, address recipient, uint64 epochId) = _checkHead(tierOutput);
+        _checkLists(tierOutput, 159);
+        _checkDomain(tierOutput, 223);
+        _checkWindow(tierOutput, 251);
         
-        // Verify merkle root matches
-        if (proofMerkleRoot != merkleRoot) revert InvalidMerkleRoot();
+        bytes32 proofTierTableDigest;
+        uint256 tier;
+        assembly {
+            proofTierTableDigest := calldataload(add(tierOutput.offset, 126))
+            tier := byte(0, calldataload(add(tierOutput.offset, 158)))
+        }
         
-        // Check nullifier hasn't been used
-        if (nullifiers[nullifier]) revert AlreadyClaimed();
+        // The tier must be computed against this campaign's table
+        if (tierTableDigest == bytes32(0) || proofTierTableDigest != tierTableDigest) {
+            revert InvalidTierTable();
+        }
+        if (tier >= tierRewards.length) revert InvalidTier();
+        uint256 amount = tierRewards[tier];
         
         // Verify the proof
-        bytes32 journalDigest = sha256(claimOutput);
+        bytes32 journalDigest = sha256(tierOutput);
         try VERIFIER.verify(seal, IMAGE_ID, journalDigest) {
             // Mark nullifier as used
             nullifiers[nullifier] = true;
             
-            // Transfer reward
-            (bool success,) = msg.sender.call{value: rewardAmount}("");
+            // Transfer the tier reward
+            (bool success,) = recipient.call{value: amount}("");
             if (!success) revert TransferFailed();
             
-            emit Claimed(nullifier, msg.sender, rewardAmount, epochId);
+            emit Claimed(nullifier, recipient, amount, epochId);
         } catch {
             revert InvalidProof();
         }
     }
     
+    /// @notice Claim every allocation proven in one batch receipt
+    /// @param seal The RISC Zero proof of the batch guest
+    /// @param batchOutput The batch claim output from the batch guest program
+    /// @dev batchOutput contains: merkleRoot (32 bytes) + epochId (8 bytes) + campaignId (32 bytes)
+    ///      + ownershipVerified (1 byte) + hashScheme (1 byte) + exclusionRoot (32 bytes)
+    ///      + policyCommitment (32 bytes) + chainId (8 bytes) + verifyingContract (20 bytes),
+    ///      followed by nullifier (32 bytes) + recipient (20 bytes) + amount (32 bytes) for each
+    ///      claim. The guest rejects repeated nullifiers within the
+    ///      batch; any nullifier claimed before reverts the whole batch. Only allocation
+    ///      campaigns can be claimed in batches.
+    function claimBatch(bytes calldata seal, bytes calldata batchOutput)
+        external
+        whenNotPaused
+    {
+        _claimBatch(seal, batchImageId, batchOutput, 0);
+    }
+    
+    /// @notice Claim every allocation of an aggregate of individually proven claims
+    /// @param seal The RISC Zero proof of the aggregation guest
+    /// @param aggregateOutput The aggregate claim output from the aggregation guest program
+    /// @dev aggregateOutput contains the image ID the claim receipts were proven under
+    ///      (32 bytes) followed by a batch claim output; see claimBatch. The aggregation guest
+    ///      verifies each claim receipt in-guest, so the claimers' inputs never reach the
+    ///      aggregator.
+    function claimAggregate(bytes calldata seal, bytes calldata aggregateOutput)
+        external
+        whenNotPaused
+    {
+        require(aggregateOutput.length >= 32, "Invalid batch output length");
+        
+        // The folded receipts must come from this campaign's claim guest
+        if (bytes32(aggregateOutput[:32]) != IMAGE_ID) revert InvalidImageId();
+        
+        _claimBatch(seal, aggregateImageId, aggregateOutput, 32);
+    }
+    
+    /// @notice Set the image ID of the batch claim guest
+    /// @param _batchImageId Image ID of the batch guest program, or 0 to disable batch claims
+    function setBatchImageId(bytes32 _batchImageId) external onlyOwner {
+        batchImageId = _batchImageId;
+        emit BatchImageIdUpdated(_batchImageId);
+    }
+    
+    /// @notice Set the image ID of the guest aggregating claim receipts
+    /// @param _aggregateImageId Image ID of the aggregation guest program, or 0 to disable it
+    function setAggregateImageId(bytes32 _aggregateImageId) external onlyOwner {
+        aggregateImageId = _aggregateImageId;
+        emit AggregateImageIdUpdated(_aggregateImageId);
+    }
+    
+    /// @notice Set the tier table and the payout of each tier
+    /// @param _tierTableDigest keccak256(abi.encodePacked(lowerBounds)) of the tier table
+    /// @param _tierRewards Payout per tier, one entry per lower bound
+    function setTiers(bytes32 _tierTableDigest, uint256[] calldata _tierRewards)
+        external
+        onlyOwner
+    {
+        if (_tierTableDigest == bytes32(0) || _tierRewards.length == 0) {
+            revert InvalidTierTable();
+        }
+        
+        tierTableDigest = _tierTableDigest;
+        tierRewards = _tierRewards;
+        
+        emit TiersUpdated(_tierTableDigest, _tierRewards);
+    }
+    
+    /// @notice Accept or revoke proofs against any one of `roots`
+    /// @param roots Eligibility roots, in the order the guest commits them
+    /// @param accepted Whether proofs against the set are accepted
+    /// @dev Widens the anonymity set to every list in the set. Like any proof, proofs
+    ///      against the set survive rotateRoot but not setEpoch
+    function setRootSet(bytes32[] calldata roots, bool accepted) external onlyOwner {
+        if (roots.length == 0) revert InvalidMerkleRoot();
+        
+        bytes32 rootSet = keccak256(abi.encodePacked(roots));
+        rootSets[rootSet] = accepted;
+        
+        emit RootSetUpdated(rootSet, accepted);
+    }
+    
+    /// @notice Set the exclusion list claimers must prove not to be on
+    /// @param _exclusionRoot Root of the sparse exclusion tree, or 0 to drop the list
+    /// @dev Proofs against the previous root are rejected once it changes
+    function setExclusionRoot(bytes32 _exclusionRoot) external onlyOwner {
+        exclusionRoot = _exclusionRoot;
+        emit ExclusionRootUpdated(_exclusionRoot);
+    }
+    
+    /// @notice Require claimers to be on `threshold` of the lists with the given roots
+    /// @param roots Roots of the additional lists, in the order the guest commits them
+    /// @param threshold Number of lists required: roots.length for AND, 1 for OR
+    /// @dev Pass no roots and a zero threshold to drop the policy
+    function setListPolicy(bytes32[] calldata roots, uint256 threshold) external onlyOwner {
+        bytes32 commitment;
+        if (roots.length != 0 || threshold != 0) {
+            if (threshold == 0 || threshold > roots.length) revert InvalidListPolicy();
+            commitment = keccak256(abi.encodePacked(threshold, roots));
+        }
+        
+        listPolicy = commitment;
+        emit ListPolicyUpdated(commitment, roots, threshold);
+    }
+    
     /// @notice Set new epoch with new Merkle root
     /// @param _merkleRoot New Merkle root
+    /// @dev Vesting allocations republished in the new root continue their tranche chain,
+    ///      so what already vested is not paid out again
     function setEpoch(bytes32 _merkleRoot) external onlyOwner {
         if (_merkleRoot == bytes32(0)) revert InvalidMerkleRoot();
         
@@ -153,11 +429,19 @@ contract ZKAirdrop {
         emit EpochUpdated(currentEpoch, _merkleRoot);
     }
     
-    /// @notice Update reward amount
-    /// @param _rewardAmount New reward amount
-    function setRewardAmount(uint256 _rewardAmount) external onlyOwner {
-        rewardAmount = _rewardAmount;
-        emit RewardAmountUpdated(_rewardAmount);
+    /// @notice Replace the Merkle root without starting a new epoch
+    /// @param _merkleRoot New Merkle root
+    /// @dev Claims already proven against the previous root stay valid until the next
+    ///      setEpoch. Nullifiers do not depend on the root, so an address on both lists
+    ///      still claims once per epoch.
+    function rotateRoot(bytes32 _merkleRoot) external onlyOwner {
+        if (_merkleRoot == bytes32(0)) revert InvalidMerkleRoot();
+        
+        bytes32 previousRoot = merkleRoot;
+        retiredRoots[previousRoot] = currentEpoch;
+        merkleRoot = _merkleRoot;
+        
+        emit RootRotated(currentEpoch, previousRoot, _merkleRoot);
     }
     
     /// @notice Toggle pause state
@@ -182,6 +466,260 @@ contract ZKAirdrop {
     /// @notice Receive ETH
     receive() external payable {}
     
+    /// @notice Decode and check the fields shared by claim and tier outputs
+    function _checkHead(bytes calldata claimOutput)
+        internal
+        view
+        returns (bytes32 nullifier, address recipient, uint64 epochId)
+    {
+        bytes32 proofMerkleRoot;
+        bytes32 campaignId;
+        uint256 ownershipVerified;
+        uint256 hashScheme;
+        
+        assembly {
+            // Load merkleRoot (first 32 bytes)
+            proofMerkleRoot := calldataload(claimOutput.offset)
+            // Load nullifier (next 32 bytes)
+            nullifier := calldataload(add(claimOutput.offset, 32))
+            // Load epochId (last 8 bytes)
+            // calldataload loads 32 bytes, but we only want the first 8 bytes as uint64
+            let epochData := calldataload(add(claimOutput.offset, 64))
+            // Shift right to get the uint64 from the left-most 8 bytes
+            epochId := shr(192, epochData)
+            // Load recipient (20 bytes after the epoch)
+            recipient := shr(96, calldataload(add(claimOutput.offset, 72)))
+            // Load campaignId, the ownership flag byte and the hash scheme byte
+            campaignId := calldataload(add(claimOutput.offset, 92))
+            ownershipVerified := byte(0, calldataload(add(claimOutput.offset, 124)))
+            hashScheme := byte(0, calldataload(add(claimOutput.offset, 125)))
+        }
+        
+        _checkCampaign(proofMerkleRoot, epochId, campaignId, ownershipVerified, hashScheme);
+        
+        // Check nullifier hasn't been used
+        if (nullifiers[nullifier]) revert AlreadyClaimed();
+    }
+    
+    /// @notice Verify a batch claim output starting at `start` of `output` and pay its entries
+    function _claimBatch(bytes calldata seal, bytes32 imageId, bytes calldata output, uint256 start)
+        internal
+    {
+        require(
+            output.length > start + BATCH_HEAD_LENGTH
+                && (output.length - start - BATCH_HEAD_LENGTH) % BATCH_ENTRY_LENGTH == 0,
+            "Invalid batch output length"
+        );
+        if (imageId == bytes32(0)) revert BatchClaimsDisabled();
+        if (MIN_BALANCE != 0) revert InvalidThreshold();
+        if (tierTableDigest != bytes32(0)) revert TierClaimRequired();
+        
+        uint64 epochId = _checkBatchHead(output, start);
+        _checkLists(output, start + 74);
+        _checkDomain(output, start + 138);
+        
+        // Verify the proof
+        bytes32 journalDigest = sha256(output);
+        try VERIFIER.verify(seal, imageId, journalDigest) {
+            _payBatch(output, start + BATCH_HEAD_LENGTH, epochId);
+        } catch {
+            revert InvalidProof();
+        }
+    }
+    
+    /// @notice Decode and check the fields of a batch claim output shared by every claim
+    function _checkBatchHead(bytes calldata output, uint256 start)
+        internal
+        view
+        returns (uint64 epochId)
+    {
+        bytes32 proofMerkleRoot;
+        bytes32 campaignId;
+        uint256 ownershipVerified;
+        uint256 hashScheme;
+        
+        assembly {
+            let head := add(output.offset, start)
+            proofMerkleRoot := calldataload(head)
+            epochId := shr(192, calldataload(add(head, 32)))
+            campaignId := calldataload(add(head, 40))
+            ownershipVerified := byte(0, calldataload(add(head, 72)))
+            hashScheme := byte(0, calldataload(add(head, 73)))
+        }
+        
+        _checkCampaign(proofMerkleRoot, epochId, campaignId, ownershipVerified, hashScheme);
+    }
+    
+    /// @notice Check the epoch, campaign, ownership flag, hash scheme and root of a proof
+    function _checkCampaign(
+        bytes32 proofMerkleRoot,
+        uint64 epochId,
+        bytes32 campaignId,
+        uint256 ownershipVerified,
+        uint256 hashScheme
+    ) internal view {
+        // Verify epoch matches
+        if (epochId != currentEpoch) revert InvalidEpoch();
+        
+        // Verify the proof was made for this campaign
+        if (campaignId != CAMPAIGN_ID) revert InvalidCampaign();
+        
+        // Verify ownership was proven if this campaign requires it
+        if (REQUIRE_OWNERSHIP && ownershipVerified != 1) revert OwnershipNotProven();
+        
+        // Verify the proof used the same hash scheme as the published roots
+        if (hashScheme != HASH_SCHEME) revert InvalidHashScheme();
+        
+        // Verify merkle root matches, or the proof is against an accepted root set or a
+        // root retired this epoch
+        if (
+            proofMerkleRoot != merkleRoot && !rootSets[proofMerkleRoot]
+                && retiredRoots[proofMerkleRoot] != currentEpoch
+        ) revert InvalidMerkleRoot();
+    }
+    
+    /// @notice Check the exclusion root and list policy at `offset` match the current ones
+    function _checkLists(bytes calldata output, uint256 offset) internal view {
+        bytes32 proofExclusionRoot;
+        bytes32 policyCommitment;
+        assembly {
+            proofExclusionRoot := calldataload(add(output.offset, offset))
+            policyCommitment := calldataload(add(output.offset, add(offset, 32)))
+        }
+        
+        if (proofExclusionRoot != exclusionRoot) revert InvalidExclusionRoot();
+        if (policyCommitment != listPolicy) revert InvalidListPolicy();
+    }
+    
+    /// @notice Check the chain ID and verifying contract at `offset` name this deployment
+    function _checkDomain(bytes calldata output, uint256 offset) internal view {
+        uint256 chainId;
+        address verifyingContract;
+        assembly {
+            chainId := shr(192, calldataload(add(output.offset, offset)))
+            verifyingContract := shr(96, calldataload(add(output.offset, add(offset, 8))))
+        }
+        
+        if (chainId != block.chainid || verifyingContract != address(this)) revert InvalidDomain();
+    }
+    
+    /// @notice Check the block time falls in the notBefore/expiresAt window at `offset`;
+    ///         a zero bound is open
+    function _checkWindow(bytes calldata output, uint256 offset) internal view {
+        uint256 notBefore;
+        uint256 expiresAt;
+        assembly {
+            notBefore := shr(192, calldataload(add(output.offset, offset)))
+            expiresAt := shr(192, calldataload(add(output.offset, add(offset, 8))))
+        }
+        
+        if (block.timestamp < notBefore) revert ClaimNotYetValid();
+        if (expiresAt != 0 && block.timestamp > expiresAt) revert ClaimExpired();
+    }
+    
+    /// @notice Payout of a claim: the proven allocation, or the fixed reward for a proven threshold
+    function _claimAmount(bytes calldata claimOutput) internal view returns (uint256) {
+        uint256 amount;
+        uint256 minBalance;
+        assembly {
+            amount := calldataload(add(claimOutput.offset, 126))
+            minBalance := calldataload(add(claimOutput.offset, 262))
+        }
+        
+        // The proof must be for this campaign's threshold (or for none)
+        if (minBalance != MIN_BALANCE) revert InvalidThreshold();
+        
+        // Tier campaigns pay the proven tier, never a proven amount
+        if (tierTableDigest != bytes32(0)) revert TierClaimRequired();
+        
+        return MIN_BALANCE == 0 ? amount : THRESHOLD_REWARD;
+    }
+    
+    /// @notice Check the vesting fields of a claim output
+    function _checkTranche(bytes calldata claimOutput, bytes32 nullifier) internal view {
+        uint64 claimTimestamp;
+        uint256 claimedAmount;
+        assembly {
+            claimTimestamp := shr(192, calldataload(add(claimOutput.offset, 190)))
+            claimedAmount := calldataload(add(claimOutput.offset, 198))
+        }
+        
+        // Vesting cannot be evaluated at a time that has not been reached
+        if (claimTimestamp > block.timestamp) revert InvalidTimestamp();
+        
+        // Only the first tranche may be claimed without a preceding claim
+        if (claimedAmount != 0 && !openTranches[nullifier]) revert TrancheNotOpen();
+    }
+    
+    /// @notice Close the claimed tranche and open the one after it
+    function _openNextTranche(bytes calldata claimOutput, bytes32 nullifier) internal {
+        bytes32 nextNullifier;
+        assembly {
+            nextNullifier := calldataload(add(claimOutput.offset, 230))
+        }
+        
+        delete openTranches[nullifier];
+        if (nextNullifier != bytes32(0)) openTranches[nextNullifier] = true;
+    }
+    
+    /// @notice Pay the committed relayer fee out of `amount` and return it
+    function _payRelayer(bytes calldata claimOutput, bytes32 nullifier, uint256 amount)
+        internal
+        returns (uint256 fee)
+    {
+        address relayer;
+        assembly {
+            relayer := shr(96, calldataload(add(claimOutput.offset, 358)))
+            fee := calldataload(add(claimOutput.offset, 378))
+        }
+        if (fee == 0) return 0;
+        
+        // The fee is a bounded cut of the payout; without a committed relayer
+        // it goes to whoever submits the claim
+        if (fee > amount) revert InvalidFee();
+        if (relayer == address(0)) relayer = msg.sender;
+        
+        (bool success,) = relayer.call{value: fee}("");
+        if (!success) revert TransferFailed();
+        
+        emit RelayerPaid(nullifier, relayer, fee);
+    }
+    
+    /// @notice Transfer the basket entries that follow the fixed claim output
+    function _payBasket(address recipient, bytes calldata claimOutput) internal {
+        for (uint256 i = CLAIM_HEAD_LENGTH; i < claimOutput.length; i += BASKET_ENTRY_LENGTH) {
+            address token;
+            uint256 tokenAmount;
+            assembly {
+                token := shr(96, calldataload(add(claimOutput.offset, i)))
+                tokenAmount := calldataload(add(claimOutput.offset, add(i, 20)))
+            }
+            if (!IERC20(token).transfer(recipient, tokenAmount)) revert TransferFailed();
+        }
+    }
+    
+    /// @notice Mark every nullifier of the batch entries from `start` on as used and pay its recipient
+    function _payBatch(bytes calldata batchOutput, uint256 start, uint64 epochId) internal {
+        for (uint256 i = start; i < batchOutput.length; i += BATCH_ENTRY_LENGTH) {
+            bytes32 nullifier;
+            address recipient;
+            uint256 amount;
+            assembly {
+                nullifier := calldataload(add(batchOutput.offset, i))
+                recipient := shr(96, calldataload(add(batchOutput.offset, add(i, 32))))
+                amount := calldataload(add(batchOutput.offset, add(i, 52)))
+            }
+            
+            if (nullifiers[nullifier]) revert AlreadyClaimed();
+            nullifiers[nullifier] = true;
+            
+            (bool success,) = recipient.call{value: amount}("");
+            if (!success) revert TransferFailed();
+            
+            emit Claimed(nullifier, recipient, amount, epochId);
+        }
+    }
+    
     function _onlyOwner() internal view {
         if (msg.sender != owner) revert Unauthorized();
     }
@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+/// @notice Minimal ERC-20 interface used to pay out basket allocations
+interface IERC20 {
+    /// @notice Transfer `amount` tokens to `to`
+    /// @param to Recipient of the tokens
+    /// @param amount Amount to transfer
+    /// @return True on success
+    function transfer(address to, uint256 amount) external returns (bool);
+}
@@ -3,6 +3,7 @@ pragma solidity ^0.8.20;
 
 import {Test} from "forge-std/Test.sol";
 import {ZKAirdrop} from "../src/ZKAirdrop.sol";
+import {IERC20} from "../src/interfaces/IERC20.sol";
 import {IRiscZeroVerifier} from "../src/interfaces/IRiscZeroVerifier.sol";
 
 /// @notice Mock verifier for testing
@@ -22,15 +23,35 @@ contract MockVerifier is IRiscZeroVerifier {
     }
 }
 
+/// @notice Mock ERC-20 for basket payouts
+contract MockToken is IERC20 {
+    mapping(address => uint256) public balanceOf;
+    
+    function mint(address to, uint256 amount) external {
+        balanceOf[to] += amount;
+    }
+    
+    function transfer(address to, uint256 amount) external override returns (bool) {
+        balanceOf[msg.sender] -= amount;
+        balanceOf[to] += amount;
+        return true;
+    }
+}
+
 contract ZKAirdropTest is Test {
     ZKAirdrop public airdrop;
     MockVerifier public verifier;
     
     bytes32 constant IMAGE_ID = bytes32(uint256(0x123456));
     bytes32 constant MERKLE_ROOT = bytes32(uint256(0xabcdef));
-    uint256 constant REWARD_AMOUNT = 1 ether;
+    uint256 constant AMOUNT = 1 ether;
+    bytes32 constant EMPTY_BASKET = bytes32(0);
+    uint64 constant TIMESTAMP = 1_700_000_000;
+    bytes32 constant CAMPAIGN_ID = bytes32(uint256(0xca));
+    uint8 constant HASH_SCHEME = 0; // SHA-256
     
     address constant USER = address(0x1);
+    address constant RELAYER = address(0x2);
     
     function setUp() public {
         verifier = new MockVerifier();
@@ -38,18 +59,25 @@ contract ZKAirdropTest is Test {
             address(verifier),
             IMAGE_ID,
             MERKLE_ROOT,
-            REWARD_AMOUNT
+            CAMPAIGN_ID,
+            true,
+            HASH_SCHEME,
+            0,
+            0
         );
         
         // Fund the contract
         vm.deal(address(airdrop), 100 ether);
+        vm.warp(TIMESTAMP);
     }
     
     function testInitialState() public view {
         assertEq(address(airdrop.VERIFIER()), address(verifier));
         assertEq(airdrop.IMAGE_ID(), IMAGE_ID);
         assertEq(airdrop.merkleRoot(), MERKLE_ROOT);
-        assertEq(airdrop.rewardAmount(), REWARD_AMOUNT);
+        assertEq(airdrop.CAMPAIGN_ID(), CAMPAIGN_ID);
+        assertTrue(airdrop.REQUIRE_OWNERSHIP());
+        assertEq(airdrop.HASH_SCHEME(), HASH_SCHEME);
         assertEq(airdrop.currentEpoch(), 1);
         assertEq(airdrop.paused(), false);
     }
@@ -57,12 +85,41 @@ contract ZKAirdropTest is Test {
     function testSuccessfulClaim() public {
         bytes32 nullifier = keccak256("test-nullifier");
         
-        // Encode claim output: merkleRoot (32) + nullifier (32) + epochId (8)
-        // Use abi.encode to ensure proper padding
+        // Encode claim output: merkleRoot (32) + nullifier (32) + epochId (8) + recipient (20)
+        // + campaignId (32) + ownershipVerified (1) + hashScheme (1) + amount (32) + basketDigest (32)
+        // + timestamp (8) + claimedAmount (32) + nextNullifier (32) + minBalance (32)
+        // + exclusionRoot (32) + policyCommitment (32) + relayer (20) + fee (32)
+        // + chainId (8) + verifyingContract (20) + notBefore (8) + expiresAt (8)
+        // Use abi.encode to ensure proper padding; the fields are packed in groups
+        // to keep the encoder's stack shallow
         bytes memory claimOutput = abi.encodePacked(
-            MERKLE_ROOT,      // 32 bytes
-            nullifier,        // 32 bytes
-            uint64(1)         // 8 bytes, will be padded correctly
+            abi.encodePacked(
+                MERKLE_ROOT,      // 32 bytes
+                nullifier,        // 32 bytes
+                uint64(1),        // 8 bytes, will be padded correctly
+                USER,             // 20 bytes
+                CAMPAIGN_ID,      // 32 bytes
+                true,             // 1 byte
+                HASH_SCHEME       // 1 byte
+            ),
+            abi.encodePacked(
+                AMOUNT,           // 32 bytes
+                EMPTY_BASKET,     // 32 bytes
+                TIMESTAMP,        // 8 bytes
+                uint256(0),       // 32 bytes, nothing claimed before
+                bytes32(0),       // 32 bytes, no next tranche
+                uint256(0),       // 32 bytes, not a threshold campaign
+                bytes32(0),       // 32 bytes, no exclusion list
+                bytes32(0)        // 32 bytes, no list policy
+            ),
+            abi.encodePacked(
+                address(0),       // 20 bytes, no relayer
+                uint256(0),       // 32 bytes, no relayer fee
+                uint64(block.chainid), // 8 bytes
+                address(airdrop), // 20 bytes
+                uint64(0),        // 8 bytes, valid from the start
+                uint64(0)         // 8 bytes, never expires
+            )
         );
         
         bytes memory seal = hex"1234"; // Mock seal
@@ -72,19 +129,866 @@ contract ZKAirdropTest is Test {
         vm.prank(USER);
         airdrop.claim(seal, claimOutput);
         
-        assertEq(USER.balance, balanceBefore + REWARD_AMOUNT);
+        assertEq(USER.balance, balanceBefore + AMOUNT);
         assertTrue(airdrop.isNullifierUsed(nullifier));
     }
     
-    function testCannotClaimTwice() public {
+    /// @notice Chain ID and verifying contract committed for `target`
+    function _domain(ZKAirdrop target) internal view returns (bytes memory) {
+        return abi.encodePacked(uint64(block.chainid), address(target));
+    }
+    
+    function testClaimBoundToDeployment() public {
+        bytes memory claimOutput = _relayedClaim(address(0), 0);
+        
+        // A proof for this deployment is not valid on another one
+        ZKAirdrop otherDrop = new ZKAirdrop(
+            address(verifier),
+            IMAGE_ID,
+            MERKLE_ROOT,
+            CAMPAIGN_ID,
+            true,
+            HASH_SCHEME,
+            0,
+            0
+        );
+        vm.deal(address(otherDrop), 10 ether);
+        vm.expectRevert(ZKAirdrop.InvalidDomain.selector);
+        otherDrop.claim(hex"1234", claimOutput);
+        
+        // Nor on another chain
+        vm.chainId(block.chainid + 1);
+        vm.expectRevert(ZKAirdrop.InvalidDomain.selector);
+        airdrop.claim(hex"1234", claimOutput);
+    }
+    
+    function testClaimPaysCommittedRecipient() public {
         bytes32 nullifier = keccak256("test-nullifier");
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                nullifier,
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        
+        uint256 userBalanceBefore = USER.balance;
+        uint256 relayerBalanceBefore = RELAYER.balance;
+        
+        // Anyone can submit the claim, but only the committed recipient is paid
+        vm.prank(RELAYER);
+        airdrop.claim(hex"1234", claimOutput);
+        
+        assertEq(USER.balance, userBalanceBefore + AMOUNT);
+        assertEq(RELAYER.balance, relayerBalanceBefore);
+    }
+    
+    function _relayedClaim(address relayer, uint256 fee) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(relayer, fee, _domain(airdrop), uint64(0), uint64(0))
+        );
+    }
+    
+    function testRelayerFeePaidToCommittedRelayer() public {
+        uint256 fee = 0.01 ether;
+        uint256 userBalanceBefore = USER.balance;
+        uint256 relayerBalanceBefore = RELAYER.balance;
+        
+        // Any submitter can relay the claim, but the fee goes to the committed relayer
+        vm.prank(address(0x3));
+        airdrop.claim(hex"1234", _relayedClaim(RELAYER, fee));
         
+        assertEq(USER.balance, userBalanceBefore + AMOUNT - fee);
+        assertEq(RELAYER.balance, relayerBalanceBefore + fee);
+    }
+    
+    function testOpenRelayerFeePaidToSubmitter() public {
+        uint256 fee = 0.01 ether;
+        uint256 relayerBalanceBefore = RELAYER.balance;
+        
+        vm.prank(RELAYER);
+        airdrop.claim(hex"1234", _relayedClaim(address(0), fee));
+        
+        assertEq(RELAYER.balance, relayerBalanceBefore + fee);
+    }
+    
+    function testRelayerFeeBoundedByPayout() public {
+        vm.expectRevert(ZKAirdrop.InvalidFee.selector);
+        airdrop.claim(hex"1234", _relayedClaim(RELAYER, AMOUNT + 1));
+    }
+    
+    function _windowClaim(uint64 notBefore, uint64 expiresAt) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), notBefore, expiresAt)
+        );
+    }
+    
+    function testClaimValidityWindow() public {
+        uint64 hourLater = TIMESTAMP + 1 hours;
+        
+        vm.expectRevert(ZKAirdrop.ClaimNotYetValid.selector);
+        airdrop.claim(hex"1234", _windowClaim(hourLater, 0));
+        
+        vm.expectRevert(ZKAirdrop.ClaimExpired.selector);
+        airdrop.claim(hex"1234", _windowClaim(0, TIMESTAMP - 1));
+        
+        // Both bounds are inclusive
+        bytes memory claimOutput = _windowClaim(TIMESTAMP, hourLater);
+        vm.warp(hourLater + 1);
+        vm.expectRevert(ZKAirdrop.ClaimExpired.selector);
+        airdrop.claim(hex"1234", claimOutput);
+        
+        vm.warp(hourLater);
+        uint256 balanceBefore = USER.balance;
+        airdrop.claim(hex"1234", claimOutput);
+        assertEq(USER.balance, balanceBefore + AMOUNT);
+    }
+    
+    function testClaimPaysCommittedAmount() public {
+        uint256 amount = 2.5 ether;
         bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                amount,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        
+        uint256 balanceBefore = USER.balance;
+        
+        airdrop.claim(hex"1234", claimOutput);
+        
+        assertEq(USER.balance, balanceBefore + amount);
+    }
+    
+    function testClaimPaysBasket() public {
+        MockToken tokenA = new MockToken();
+        MockToken tokenB = new MockToken();
+        tokenA.mint(address(airdrop), 1000);
+        tokenB.mint(address(airdrop), 1000);
+        
+        // Digest is checked in the guest; the contract pays the committed entries
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                keccak256(abi.encodePacked(address(tokenA), uint256(300), address(tokenB), uint256(7))),
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(
+                address(0),
+                uint256(0),
+                _domain(airdrop),
+                uint64(0),
+                uint64(0),
+                address(tokenA),
+                uint256(300),
+                address(tokenB),
+                uint256(7)
+            )
+        );
+        
+        airdrop.claim(hex"1234", claimOutput);
+        
+        assertEq(tokenA.balanceOf(USER), 300);
+        assertEq(tokenB.balanceOf(USER), 7);
+        assertEq(tokenA.balanceOf(address(airdrop)), 700);
+    }
+    
+    function testInvalidBasketLength() public {
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0), address(0x3))
+        );
+        
+        vm.expectRevert("Invalid claim output length");
+        airdrop.claim(hex"1234", claimOutput);
+    }
+    
+    function _vestingClaim(
+        bytes32 nullifier,
+        uint256 amount,
+        uint256 claimedAmount,
+        bytes32 nextNullifier
+    ) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                nullifier,
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                amount,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                claimedAmount,
+                nextNullifier,
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+    }
+    
+    function testVestingTranches() public {
+        bytes32 first = keccak256("tranche-0");
+        bytes32 second = keccak256("tranche-100");
+        bytes32 third = keccak256("tranche-250");
+        uint256 balanceBefore = USER.balance;
+        
+        // First tranche pays 100 and opens the tranche starting at 100
+        airdrop.claim(hex"1234", _vestingClaim(first, 100, 0, second));
+        assertTrue(airdrop.openTranches(second));
+        
+        // Second tranche pays the next 150
+        airdrop.claim(hex"1234", _vestingClaim(second, 150, 100, third));
+        assertFalse(airdrop.openTranches(second));
+        assertTrue(airdrop.openTranches(third));
+        
+        assertEq(USER.balance, balanceBefore + 250);
+    }
+    
+    function testVestingCarriesOverEpochs() public {
+        bytes32 first = keccak256("tranche-0");
+        bytes32 second = keccak256("tranche-100");
+        airdrop.claim(hex"1234", _vestingClaim(first, 100, 0, second));
+        
+        // The republished leaf proves the same tranche nullifiers in the new epoch
+        airdrop.setEpoch(MERKLE_ROOT);
+        bytes memory claimOutput = _vestingClaim(first, 100, 0, second);
+        claimOutput[71] = bytes1(uint8(2)); // last byte of the epoch ID
+        vm.expectRevert(ZKAirdrop.AlreadyClaimed.selector);
+        airdrop.claim(hex"1234", claimOutput);
+        
+        uint256 balanceBefore = USER.balance;
+        claimOutput = _vestingClaim(second, 150, 100, bytes32(0));
+        claimOutput[71] = bytes1(uint8(2));
+        airdrop.claim(hex"1234", claimOutput);
+        assertEq(USER.balance, balanceBefore + 150);
+    }
+    
+    function testTrancheMustBeOpened() public {
+        // Claiming from an arbitrary claimed amount would pay the overlap twice
+        vm.expectRevert(ZKAirdrop.TrancheNotOpen.selector);
+        airdrop.claim(hex"1234", _vestingClaim(keccak256("tranche-50"), 200, 50, bytes32(0)));
+    }
+    
+    function testFutureTimestamp() public {
+        vm.warp(TIMESTAMP - 1);
+        
+        vm.expectRevert(ZKAirdrop.InvalidTimestamp.selector);
+        airdrop.claim(hex"1234", _vestingClaim(keccak256("tranche-0"), 100, 0, bytes32(0)));
+    }
+    
+    function _thresholdClaim(ZKAirdrop target, uint256 amount, uint256 minBalance)
+        internal
+        view
+        returns (bytes memory)
+    {
+        return abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                amount,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                minBalance,
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(target), uint64(0), uint64(0))
+        );
+    }
+    
+    function testThresholdClaimPaysFixedReward() public {
+        ZKAirdrop thresholdDrop = new ZKAirdrop(
+            address(verifier),
+            IMAGE_ID,
+            MERKLE_ROOT,
+            CAMPAIGN_ID,
+            true,
+            HASH_SCHEME,
+            1000,
+            0.5 ether
+        );
+        vm.deal(address(thresholdDrop), 10 ether);
+        uint256 balanceBefore = USER.balance;
+        
+        thresholdDrop.claim(hex"1234", _thresholdClaim(thresholdDrop, 0, 1000));
+        
+        assertEq(USER.balance, balanceBefore + 0.5 ether);
+    }
+    
+    function testThresholdMustMatch() public {
+        // A proof for a lower threshold is rejected
+        ZKAirdrop thresholdDrop = new ZKAirdrop(
+            address(verifier),
+            IMAGE_ID,
             MERKLE_ROOT,
-            nullifier,
+            CAMPAIGN_ID,
+            true,
+            HASH_SCHEME,
+            1000,
+            0.5 ether
+        );
+        vm.expectRevert(ZKAirdrop.InvalidThreshold.selector);
+        thresholdDrop.claim(hex"1234", _thresholdClaim(thresholdDrop, 0, 999));
+        
+        // Allocation campaigns reject threshold proofs
+        vm.expectRevert(ZKAirdrop.InvalidThreshold.selector);
+        airdrop.claim(hex"1234", _thresholdClaim(airdrop, 0, 1000));
+    }
+    
+    function _tierClaim(bytes32 tableDigest, uint8 tier) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            MERKLE_ROOT,
+            keccak256("test-nullifier"),
+            uint64(1),
+            USER,
+            CAMPAIGN_ID,
+            true,
+            HASH_SCHEME,
+            tableDigest,
+            tier,
+            bytes32(0),
+            bytes32(0),
+            abi.encodePacked(_domain(airdrop), uint64(0), uint64(0))
+        );
+    }
+    
+    function _setTiers() internal returns (bytes32 tableDigest) {
+        uint256[] memory lowerBounds = new uint256[](3);
+        lowerBounds[0] = 10;
+        lowerBounds[1] = 100;
+        lowerBounds[2] = 1000;
+        uint256[] memory rewards = new uint256[](3);
+        rewards[0] = 0.1 ether;
+        rewards[1] = 0.2 ether;
+        rewards[2] = 0.5 ether;
+        
+        tableDigest = keccak256(abi.encodePacked(lowerBounds));
+        airdrop.setTiers(tableDigest, rewards);
+    }
+    
+    function testTierClaimPaysTierReward() public {
+        bytes32 tableDigest = _setTiers();
+        uint256 balanceBefore = USER.balance;
+        
+        airdrop.claimTier(hex"1234", _tierClaim(tableDigest, 1));
+        
+        assertEq(USER.balance, balanceBefore + 0.2 ether);
+        assertTrue(airdrop.isNullifierUsed(keccak256("test-nullifier")));
+        
+        vm.expectRevert(ZKAirdrop.AlreadyClaimed.selector);
+        airdrop.claimTier(hex"1234", _tierClaim(tableDigest, 2));
+    }
+    
+    function testTierClaimChecksTable() public {
+        // Tier claims are rejected until a table is set
+        vm.expectRevert(ZKAirdrop.InvalidTierTable.selector);
+        airdrop.claimTier(hex"1234", _tierClaim(bytes32(0), 0));
+        
+        bytes32 tableDigest = _setTiers();
+        
+        vm.expectRevert(ZKAirdrop.InvalidTierTable.selector);
+        airdrop.claimTier(hex"1234", _tierClaim(keccak256("other-table"), 0));
+        
+        vm.expectRevert(ZKAirdrop.InvalidTier.selector);
+        airdrop.claimTier(hex"1234", _tierClaim(tableDigest, 3));
+        
+        vm.expectRevert("Invalid tier output length");
+        airdrop.claimTier(hex"1234", abi.encodePacked(_tierClaim(tableDigest, 0), uint8(0)));
+    }
+    
+    function testTierCampaignRejectsAllocationClaims() public {
+        _setTiers();
+        
+        vm.expectRevert(ZKAirdrop.TierClaimRequired.selector);
+        airdrop.claim(hex"1234", _windowClaim(0, 0));
+        
+        airdrop.setBatchImageId(bytes32(uint256(0xba7c4)));
+        vm.expectRevert(ZKAirdrop.TierClaimRequired.selector);
+        airdrop.claimBatch(hex"1234", _batchClaim(keccak256("batch-nullifier-2")));
+    }
+    
+    function testSetTiersOnlyOwner() public {
+        uint256[] memory rewards = new uint256[](1);
+        vm.prank(USER);
+        vm.expectRevert(ZKAirdrop.Unauthorized.selector);
+        airdrop.setTiers(keccak256("table"), rewards);
+    }
+    
+    function _listClaim(bytes32 exclusionRoot, bytes32 policyCommitment)
+        internal
+        view
+        returns (bytes memory)
+    {
+        return abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                exclusionRoot,
+                policyCommitment
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+    }
+    
+    function testExclusionRootMustMatch() public {
+        bytes32 exclusionRoot = keccak256("blocklist");
+        airdrop.setExclusionRoot(exclusionRoot);
+        
+        // Proofs without the exclusion check, or against another list, are rejected
+        vm.expectRevert(ZKAirdrop.InvalidExclusionRoot.selector);
+        airdrop.claim(hex"1234", _listClaim(bytes32(0), bytes32(0)));
+        vm.expectRevert(ZKAirdrop.InvalidExclusionRoot.selector);
+        airdrop.claim(hex"1234", _listClaim(keccak256("old-blocklist"), bytes32(0)));
+        
+        uint256 balanceBefore = USER.balance;
+        airdrop.claim(hex"1234", _listClaim(exclusionRoot, bytes32(0)));
+        assertEq(USER.balance, balanceBefore + AMOUNT);
+    }
+    
+    function testSetExclusionRootOnlyOwner() public {
+        vm.prank(USER);
+        vm.expectRevert(ZKAirdrop.Unauthorized.selector);
+        airdrop.setExclusionRoot(keccak256("blocklist"));
+    }
+    
+    function testRootSetClaim() public {
+        bytes32[] memory roots = new bytes32[](2);
+        roots[0] = keccak256("previous-root");
+        roots[1] = MERKLE_ROOT;
+        bytes32 rootSet = keccak256(abi.encodePacked(roots));
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                rootSet,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        
+        vm.expectRevert(ZKAirdrop.InvalidMerkleRoot.selector);
+        airdrop.claim(hex"1234", claimOutput);
+        
+        airdrop.setRootSet(roots, true);
+        assertTrue(airdrop.rootSets(rootSet));
+        
+        uint256 balanceBefore = USER.balance;
+        airdrop.claim(hex"1234", claimOutput);
+        assertEq(USER.balance, balanceBefore + AMOUNT);
+    }
+    
+    function testRevokeRootSet() public {
+        bytes32[] memory roots = new bytes32[](1);
+        roots[0] = MERKLE_ROOT;
+        airdrop.setRootSet(roots, true);
+        airdrop.setRootSet(roots, false);
+        assertFalse(airdrop.rootSets(keccak256(abi.encodePacked(roots))));
+        
+        vm.prank(USER);
+        vm.expectRevert(ZKAirdrop.Unauthorized.selector);
+        airdrop.setRootSet(roots, true);
+    }
+    
+    function testListPolicyMustMatch() public {
+        bytes32[] memory roots = new bytes32[](2);
+        roots[0] = keccak256("voters");
+        roots[1] = keccak256("holders");
+        airdrop.setListPolicy(roots, 2);
+        bytes32 policy = keccak256(abi.encodePacked(uint256(2), roots));
+        assertEq(airdrop.listPolicy(), policy);
+        
+        // A proof that skipped the policy, or satisfied only one list, is rejected
+        vm.expectRevert(ZKAirdrop.InvalidListPolicy.selector);
+        airdrop.claim(hex"1234", _listClaim(bytes32(0), bytes32(0)));
+        bytes32 anyPolicy = keccak256(abi.encodePacked(uint256(1), roots));
+        vm.expectRevert(ZKAirdrop.InvalidListPolicy.selector);
+        airdrop.claim(hex"1234", _listClaim(bytes32(0), anyPolicy));
+        
+        uint256 balanceBefore = USER.balance;
+        airdrop.claim(hex"1234", _listClaim(bytes32(0), policy));
+        assertEq(USER.balance, balanceBefore + AMOUNT);
+    }
+    
+    function testSetListPolicyRejectsBadThreshold() public {
+        bytes32[] memory roots = new bytes32[](1);
+        roots[0] = keccak256("voters");
+        vm.expectRevert(ZKAirdrop.InvalidListPolicy.selector);
+        airdrop.setListPolicy(roots, 2);
+        vm.expectRevert(ZKAirdrop.InvalidListPolicy.selector);
+        airdrop.setListPolicy(roots, 0);
+        
+        // No roots and no threshold drops the policy
+        airdrop.setListPolicy(roots, 1);
+        airdrop.setListPolicy(new bytes32[](0), 0);
+        assertEq(airdrop.listPolicy(), bytes32(0));
+    }
+    
+    function _batchClaim(bytes32 secondNullifier) internal view returns (bytes memory) {
+        return abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                uint64(1),
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME,
+                bytes32(0),
+                bytes32(0),
+                _domain(airdrop)
+            ),
+            abi.encodePacked(keccak256("batch-nullifier-1"), USER, AMOUNT),
+            abi.encodePacked(secondNullifier, RELAYER, 2 * AMOUNT)
+        );
+    }
+    
+    function testBatchClaimPaysEveryEntry() public {
+        bytes memory batchOutput = _batchClaim(keccak256("batch-nullifier-2"));
+        
+        vm.expectRevert(ZKAirdrop.BatchClaimsDisabled.selector);
+        airdrop.claimBatch(hex"1234", batchOutput);
+        
+        airdrop.setBatchImageId(bytes32(uint256(0xba7c4)));
+        uint256 userBefore = USER.balance;
+        uint256 relayerBefore = RELAYER.balance;
+        airdrop.claimBatch(hex"1234", batchOutput);
+        
+        assertEq(USER.balance, userBefore + AMOUNT);
+        assertEq(RELAYER.balance, relayerBefore + 2 * AMOUNT);
+        assertTrue(airdrop.isNullifierUsed(keccak256("batch-nullifier-1")));
+        assertTrue(airdrop.isNullifierUsed(keccak256("batch-nullifier-2")));
+        
+        vm.expectRevert(ZKAirdrop.AlreadyClaimed.selector);
+        airdrop.claimBatch(hex"1234", batchOutput);
+    }
+    
+    function testBatchClaimRejectsClaimedNullifier() public {
+        airdrop.setBatchImageId(bytes32(uint256(0xba7c4)));
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        airdrop.claim(hex"1234", claimOutput);
+        
+        // A nullifier claimed on its own reverts the whole batch
+        uint256 userBefore = USER.balance;
+        vm.expectRevert(ZKAirdrop.AlreadyClaimed.selector);
+        airdrop.claimBatch(hex"1234", _batchClaim(keccak256("test-nullifier")));
+        assertEq(USER.balance, userBefore);
+        assertFalse(airdrop.isNullifierUsed(keccak256("batch-nullifier-1")));
+        
+        vm.expectRevert("Invalid batch output length");
+        airdrop.claimBatch(hex"1234", abi.encodePacked(MERKLE_ROOT));
+    }
+    
+    function testAggregateClaimChecksClaimImage() public {
+        bytes memory batchOutput = _batchClaim(keccak256("batch-nullifier-2"));
+        
+        vm.expectRevert(ZKAirdrop.BatchClaimsDisabled.selector);
+        airdrop.claimAggregate(hex"1234", abi.encodePacked(IMAGE_ID, batchOutput));
+        
+        airdrop.setAggregateImageId(bytes32(uint256(0xa66)));
+        vm.expectRevert(ZKAirdrop.InvalidImageId.selector);
+        airdrop.claimAggregate(hex"1234", abi.encodePacked(bytes32(uint256(0xbad)), batchOutput));
+        
+        uint256 relayerBefore = RELAYER.balance;
+        airdrop.claimAggregate(hex"1234", abi.encodePacked(IMAGE_ID, batchOutput));
+        assertEq(RELAYER.balance, relayerBefore + 2 * AMOUNT);
+        assertTrue(airdrop.isNullifierUsed(keccak256("batch-nullifier-1")));
+        
+        // Claims paid through an aggregate cannot be batched again
+        airdrop.setBatchImageId(bytes32(uint256(0xba7c4)));
+        vm.expectRevert(ZKAirdrop.AlreadyClaimed.selector);
+        airdrop.claimBatch(hex"1234", batchOutput);
+    }
+    
+    function testSetBatchImageIdOnlyOwner() public {
+        vm.prank(USER);
+        vm.expectRevert(ZKAirdrop.Unauthorized.selector);
+        airdrop.setBatchImageId(bytes32(uint256(0xba7c4)));
+    }
+    
+    function testInvalidClaimOutputLength() public {
+        bytes memory claimOutput = abi.encodePacked(
+            MERKLE_ROOT,
+            keccak256("test-nullifier"),
             uint64(1)
         );
         
+        vm.expectRevert("Invalid claim output length");
+        airdrop.claim(hex"1234", claimOutput);
+    }
+    
+    function testWrongCampaign() public {
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                bytes32(uint256(0xbad)),
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        
+        vm.expectRevert(ZKAirdrop.InvalidCampaign.selector);
+        airdrop.claim(hex"1234", claimOutput);
+    }
+    
+    function testOwnershipRequired() public {
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                false,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        
+        vm.expectRevert(ZKAirdrop.OwnershipNotProven.selector);
+        airdrop.claim(hex"1234", claimOutput);
+    }
+    
+    function testWrongHashScheme() public {
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                keccak256("test-nullifier"),
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                uint8(1)
+            ),
+            abi.encodePacked(
+                // Keccak-256
+            AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        
+        vm.expectRevert(ZKAirdrop.InvalidHashScheme.selector);
+        airdrop.claim(hex"1234", claimOutput);
+    }
+    
+    function testCannotClaimTwice() public {
+        bytes32 nullifier = keccak256("test-nullifier");
+        
+        bytes memory claimOutput = abi.encodePacked(
+            abi.encodePacked(
+                MERKLE_ROOT,
+                nullifier,
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
+        );
+        
         bytes memory seal = hex"1234";
         
         // First claim succeeds
@@ -102,9 +1006,26 @@ contract ZKAirdropTest is Test {
         
         bytes32 nullifier = keccak256("test-nullifier");
         bytes memory claimOutput = abi.encodePacked(
-            MERKLE_ROOT,
-            nullifier,
-            uint64(1)
+            abi.encodePacked(
+                MERKLE_ROOT,
+                nullifier,
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
         );
         
         vm.prank(USER);
@@ -115,9 +1036,27 @@ contract ZKAirdropTest is Test {
     function testWrongEpoch() public {
         bytes32 nullifier = keccak256("test-nullifier");
         bytes memory claimOutput = abi.encodePacked(
-            MERKLE_ROOT,
-            nullifier,
-            uint64(999) // Wrong epoch
+            abi.encodePacked(
+                MERKLE_ROOT,
+                nullifier,
+                uint64(999),
+                // Wrong epoch
+            USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
         );
         
         vm.prank(USER);
@@ -134,15 +1073,59 @@ contract ZKAirdropTest is Test {
         assertEq(airdrop.merkleRoot(), newRoot);
     }
     
+    function testRotateRootKeepsProofsValid() public {
+        // Proven against the current root, submitted after it was rotated out
+        bytes memory claimOutput = _windowClaim(0, 0);
+        bytes32 newRoot = bytes32(uint256(0x999));
+        airdrop.rotateRoot(newRoot);
+        
+        assertEq(airdrop.currentEpoch(), 1);
+        assertEq(airdrop.merkleRoot(), newRoot);
+        
+        uint256 balanceBefore = USER.balance;
+        airdrop.claim(hex"1234", claimOutput);
+        assertEq(USER.balance, balanceBefore + AMOUNT);
+        
+        // The retired root is no longer accepted in the next epoch
+        airdrop.setEpoch(newRoot);
+        claimOutput = _windowClaim(0, 0);
+        claimOutput[71] = bytes1(uint8(2)); // last byte of the epoch ID
+        vm.expectRevert(ZKAirdrop.InvalidMerkleRoot.selector);
+        airdrop.claim(hex"1234", claimOutput);
+    }
+    
+    function testRotateRootOnlyOwner() public {
+        vm.prank(USER);
+        vm.expectRevert(ZKAirdrop.Unauthorized.selector);
+        airdrop.rotateRoot(bytes32(uint256(0x999)));
+    }
+    
     function testPauseUnpause() public {
         airdrop.togglePause();
         assertTrue(airdrop.paused());
         
         bytes32 nullifier = keccak256("test-nullifier");
         bytes memory claimOutput = abi.encodePacked(
-            MERKLE_ROOT,
-            nullifier,
-            uint64(1)
+            abi.encodePacked(
+                MERKLE_ROOT,
+                nullifier,
+                uint64(1),
+                USER,
+                CAMPAIGN_ID,
+                true,
+                HASH_SCHEME
+            ),
+            abi.encodePacked(
+                AMOUNT,
+                EMPTY_BASKET,
+                TIMESTAMP,
+                uint256(0),
+                bytes32(0),
+                uint256(0),
+                bytes32(0),
+                bytes32(0)
+            ),
+            abi.encodePacked(address(0), uint256(0), _domain(airdrop), uint64(0), uint64(0))
         );
         
         vm.prank(USER);
@@ -0,0 +1,165 @@
+//! Multi-token basket allocations
+//!
+//! A basket lists `(token, amount)` pairs that a leaf commits to on top of its
+//! native `amount`. Baskets are hashed in canonical order (ascending token
+//! address) so the same allocation always yields the same leaf.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{amount_word, compute_allocation_leaf, keccak256, MerkleHasher, VestingSchedule};
+
+/// Packed length of one basket entry: `abi.encodePacked(token, uint256(amount))`
+pub const BASKET_ENTRY_LEN: usize = 52;
+
+/// Digest of an empty basket
+pub const EMPTY_BASKET_DIGEST: [u8; 32] = [0u8; 32];
+
+/// One `(token, amount)` pair of a basket; for an NFT the amount is the token id
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TokenAllocation {
+    /// Token contract address
+    pub token: [u8; 20],
+    /// Amount (or token id) allocated
+    pub amount: u128,
+}
+
+impl TokenAllocation {
+    /// Encode as `abi.encodePacked(token, uint256(amount))`
+    pub fn to_abi_bytes(&self) -> [u8; BASKET_ENTRY_LEN] {
+        let mut bytes = [0u8; BASKET_ENTRY_LEN];
+        bytes[..20].copy_from_slice(&self.token);
+        bytes[20..].copy_from_slice(&amount_word(self.amount));
+        bytes
+    }
+
+    /// Decode a packed entry, rejecting amounts above `u128::MAX`
+    pub fn from_abi_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != BASKET_ENTRY_LEN || bytes[20..36].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+
+        let mut token = [0u8; 20];
+        token.copy_from_slice(&bytes[..20]);
+        let mut amount_bytes = [0u8; 16];
+        amount_bytes.copy_from_slice(&bytes[36..]);
+
+        Some(TokenAllocation {
+            token,
+            amount: u128::from_be_bytes(amount_bytes),
+        })
+    }
+}
+
+/// Sort a basket by token address, or `None` if a token appears twice
+pub fn canonical_basket(basket: &[TokenAllocation]) -> Option<Vec<TokenAllocation>> {
+    let mut sorted = basket.to_vec();
+    sorted.sort();
+    if !is_canonical(&sorted) {
+        return None;
+    }
+    Some(sorted)
+}
+
+/// Whether token addresses are strictly ascending
+pub fn is_canonical(basket: &[TokenAllocation]) -> bool {
+    basket.windows(2).all(|pair| pair[0].token < pair[1].token)
+}
+
+/// `keccak256` of the packed canonical basket, `EMPTY_BASKET_DIGEST` if empty,
+/// or `None` if a token appears twice
+pub fn basket_digest(basket: &[TokenAllocation]) -> Option<[u8; 32]> {
+    if basket.is_empty() {
+        return Some(EMPTY_BASKET_DIGEST);
+    }
+
+    let packed: Vec<u8> = canonical_basket(basket)?
+        .iter()
+        .flat_map(|entry| entry.to_abi_bytes())
+        .collect();
+    Some(keccak256(&packed))
+}
+
+/// One row of an eligibility list
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AllocationRecord {
+    /// Eligible address
+    pub address: [u8; 20],
+    /// Native amount (the total, for vesting records)
+    pub amount: u128,
+    /// Vesting schedule over `amount`
+    pub vesting: Option<VestingSchedule>,
+    /// Additional tokens, in any order
+    pub basket: Vec<TokenAllocation>,
+    /// Secret commitment for `NullifierScheme::SecretV2` trees
+    pub commitment: Option<[u8; 32]>,
+}
+
+impl AllocationRecord {
+    /// Leaf hash of this record, or `None` if the basket repeats a token
+    pub fn leaf<H: MerkleHasher>(&self) -> Option<[u8; 32]> {
+        compute_allocation_leaf::<H>(
+            &self.address,
+            self.amount,
+            self.vesting.as_ref(),
+            &self.basket,
+            self.commitment.as_ref(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compute_leaf, Sha256Hasher};
+
+    fn entry(token: u8, amount: u128) -> TokenAllocation {
+        TokenAllocation {
+            token: [token; 20],
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_basket_digest_is_order_independent() {
+        let a = entry(1, 100);
+        let b = entry(2, 7);
+        assert_eq!(basket_digest(&[a, b]), basket_digest(&[b, a]));
+        assert_ne!(basket_digest(&[a, b]), basket_digest(&[a, entry(2, 8)]));
+        assert_eq!(basket_digest(&[]), Some(EMPTY_BASKET_DIGEST));
+
+        // Duplicate tokens have no canonical form
+        assert_eq!(basket_digest(&[a, entry(1, 5)]), None);
+    }
+
+    #[test]
+    fn test_record_leaf() {
+        let mut record = AllocationRecord {
+            address: [9u8; 20],
+            amount: 100,
+            vesting: None,
+            basket: Vec::new(),
+            commitment: None,
+        };
+        assert_eq!(
+            record.leaf::<Sha256Hasher>(),
+            Some(compute_leaf::<Sha256Hasher>(&[9u8; 20], 100))
+        );
+
+        record.basket = vec![entry(2, 7), entry(1, 100)];
+        let leaf = record.leaf::<Sha256Hasher>().unwrap();
+        assert_ne!(leaf, compute_leaf::<Sha256Hasher>(&[9u8; 20], 100));
+
+        record.basket.reverse();
+        assert_eq!(record.leaf::<Sha256Hasher>(), Some(leaf));
+    }
+
+    #[test]
+    fn test_entry_abi_roundtrip() {
+        let token = entry(3, u128::MAX);
+        let mut bytes = token.to_abi_bytes();
+        assert_eq!(TokenAllocation::from_abi_bytes(&bytes), Some(token));
+
+        bytes[20] = 1;
+        assert_eq!(TokenAllocation::from_abi_bytes(&bytes), None);
+    }
+}
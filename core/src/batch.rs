@@ -0,0 +1,360 @@
+//! Many claims of one campaign proven in a single receipt
+//!
+//! Every claim in a batch shares the campaign fields of one `PublicInputs`
+//! and names its own recipient. The journal commits those shared fields once,
+//! followed by a nullifier, recipient and amount per claim, so the contract
+//! pays the whole batch against one seal.
+//!
+//! Claims proven separately can be folded into the same layout by the
+//! aggregation guest, which verifies each `ClaimOutput` receipt in-guest and
+//! prefixes the batch with the image ID those receipts were proven under.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{amount_word, decode_amount, ClaimInput, ClaimOutput, HashScheme};
+
+/// One claim of a batch
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchClaim {
+    /// Private claim data, verified like a single claim
+    pub input: ClaimInput,
+    /// Address that receives this claim's allocation
+    pub recipient: [u8; 20],
+}
+
+/// Payout of one claim in a `BatchClaimOutput`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchEntry {
+    /// Nullifier to prevent double-claiming
+    pub nullifier: [u8; 32],
+    /// Address that receives the allocation
+    pub recipient: [u8; 20],
+    /// Allocation proven by the leaf
+    pub amount: u128,
+}
+
+impl BatchEntry {
+    /// Length of a packed entry
+    pub const ABI_LEN: usize = 84;
+}
+
+/// Output committed to the journal by the batch guest
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchClaimOutput {
+    /// The verified Merkle root, or the `root_set_commitment` of the accepted roots
+    pub merkle_root: [u8; 32],
+    /// Epoch ID that was verified
+    pub epoch_id: u64,
+    /// Campaign the claims were made for
+    pub campaign_id: [u8; 32],
+    /// Whether every claimer proved control of their eligible address
+    pub ownership_verified: bool,
+    /// Hash function the Merkle proofs were verified with
+    pub hash_scheme: HashScheme,
+    /// Exclusion tree every claimer proved not to be in; zero without an exclusion list
+    pub exclusion_root: [u8; 32],
+    /// `ListPolicy::commitment` of the policy every claimer satisfied; zero without one
+    pub policy_commitment: [u8; 32],
+    /// Chain the claims are valid on
+    pub chain_id: u64,
+    /// `ZKAirdrop` deployment the claims are valid for
+    pub verifying_contract: [u8; 20],
+    /// One entry per claim, in input order; nullifiers are distinct
+    pub entries: Vec<BatchEntry>,
+}
+
+impl BatchClaimOutput {
+    /// Length of the fixed part of the packed journal; entries follow it
+    pub const HEAD_LEN: usize = 166;
+
+    /// Encode as `abi.encodePacked(merkleRoot, uint64(epochId), campaignId,
+    /// ownershipVerified, uint8(hashScheme), exclusionRoot, policyCommitment, uint64(chainId),
+    /// verifyingContract)`
+    /// followed by `abi.encodePacked(nullifier, recipient, uint256(amount))` for each entry
+    pub fn to_abi_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(Self::HEAD_LEN + self.entries.len() * BatchEntry::ABI_LEN);
+        bytes.extend_from_slice(&self.merkle_root);
+        bytes.extend_from_slice(&self.epoch_id.to_be_bytes());
+        bytes.extend_from_slice(&self.campaign_id);
+        bytes.push(self.ownership_verified as u8);
+        bytes.push(self.hash_scheme as u8);
+        bytes.extend_from_slice(&self.exclusion_root);
+        bytes.extend_from_slice(&self.policy_commitment);
+        bytes.extend_from_slice(&self.chain_id.to_be_bytes());
+        bytes.extend_from_slice(&self.verifying_contract);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.nullifier);
+            bytes.extend_from_slice(&entry.recipient);
+            bytes.extend_from_slice(&amount_word(entry.amount));
+        }
+        bytes
+    }
+
+    /// Fold single-claim outputs into one batch, or `None` if they are empty,
+    /// do not share their campaign fields, repeat a nullifier or are not plain
+    /// allocations (vesting, basket, threshold and relayed claims commit more than an entry)
+    pub fn from_claims(outputs: &[ClaimOutput]) -> Option<Self> {
+        let first = outputs.first()?;
+        let mut nullifiers = BTreeSet::new();
+        for output in outputs {
+            if !Self::can_fold(first, output) || !nullifiers.insert(output.nullifier) {
+                return None;
+            }
+        }
+
+        Some(BatchClaimOutput {
+            merkle_root: first.merkle_root,
+            epoch_id: first.epoch_id,
+            campaign_id: first.campaign_id,
+            ownership_verified: first.ownership_verified,
+            hash_scheme: first.hash_scheme,
+            exclusion_root: first.exclusion_root,
+            policy_commitment: first.policy_commitment,
+            chain_id: first.chain_id,
+            verifying_contract: first.verifying_contract,
+            entries: outputs
+                .iter()
+                .map(|output| BatchEntry {
+                    nullifier: output.nullifier,
+                    recipient: output.recipient,
+                    amount: output.amount,
+                })
+                .collect(),
+        })
+    }
+
+    /// Whether `output` is a plain allocation sharing the campaign fields of
+    /// `first`, the first claim of a batch; nullifiers are not checked
+    pub fn can_fold(first: &ClaimOutput, output: &ClaimOutput) -> bool {
+        let same_campaign = output.merkle_root == first.merkle_root
+            && output.epoch_id == first.epoch_id
+            && output.campaign_id == first.campaign_id
+            && output.ownership_verified == first.ownership_verified
+            && output.hash_scheme == first.hash_scheme
+            && output.exclusion_root == first.exclusion_root
+            && output.policy_commitment == first.policy_commitment
+            && output.chain_id == first.chain_id
+            && output.verifying_contract == first.verifying_contract;
+        let plain_allocation = output.basket.is_empty()
+            && output.claimed_amount == 0
+            && output.next_nullifier == [0u8; 32]
+            && output.min_balance == 0
+            && output.fee == 0
+            && output.not_before == 0
+            && output.expires_at == 0;
+        same_campaign && plain_allocation
+    }
+
+    /// Decode the packed journal layout, returning `None` on a length mismatch
+    pub fn from_abi_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::HEAD_LEN
+            || !(bytes.len() - Self::HEAD_LEN).is_multiple_of(BatchEntry::ABI_LEN)
+        {
+            return None;
+        }
+
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[0..32]);
+        let mut epoch_bytes = [0u8; 8];
+        epoch_bytes.copy_from_slice(&bytes[32..40]);
+        let mut campaign_id = [0u8; 32];
+        campaign_id.copy_from_slice(&bytes[40..72]);
+        let ownership_verified = match bytes[72] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let hash_scheme = HashScheme::from_u8(bytes[73])?;
+        let mut exclusion_root = [0u8; 32];
+        exclusion_root.copy_from_slice(&bytes[74..106]);
+        let mut policy_commitment = [0u8; 32];
+        policy_commitment.copy_from_slice(&bytes[106..138]);
+        let mut chain_id_bytes = [0u8; 8];
+        chain_id_bytes.copy_from_slice(&bytes[138..146]);
+        let mut verifying_contract = [0u8; 20];
+        verifying_contract.copy_from_slice(&bytes[146..166]);
+        let entries = bytes[Self::HEAD_LEN..]
+            .chunks(BatchEntry::ABI_LEN)
+            .map(|entry| {
+                let mut nullifier = [0u8; 32];
+                nullifier.copy_from_slice(&entry[0..32]);
+                let mut recipient = [0u8; 20];
+                recipient.copy_from_slice(&entry[32..52]);
+                Some(BatchEntry {
+                    nullifier,
+                    recipient,
+                    amount: decode_amount(&entry[52..84])?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(BatchClaimOutput {
+            merkle_root,
+            epoch_id: u64::from_be_bytes(epoch_bytes),
+            campaign_id,
+            ownership_verified,
+            hash_scheme,
+            exclusion_root,
+            policy_commitment,
+            chain_id: u64::from_be_bytes(chain_id_bytes),
+            verifying_contract,
+            entries,
+        })
+    }
+}
+
+/// Output committed to the journal by the aggregation guest
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AggregateClaimOutput {
+    /// Image ID the folded claim receipts were proven under, as `image_id_bytes`
+    pub claim_image_id: [u8; 32],
+    /// The folded claims
+    pub batch: BatchClaimOutput,
+}
+
+impl AggregateClaimOutput {
+    /// Encode as `abi.encodePacked(claimImageId)` followed by the packed batch
+    pub fn to_abi_bytes(&self) -> Vec<u8> {
+        [self.claim_image_id.as_slice(), &self.batch.to_abi_bytes()].concat()
+    }
+
+    /// Decode the packed journal layout, returning `None` on a length mismatch
+    pub fn from_abi_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 32 {
+            return None;
+        }
+
+        let mut claim_image_id = [0u8; 32];
+        claim_image_id.copy_from_slice(&bytes[..32]);
+        Some(AggregateClaimOutput {
+            claim_image_id,
+            batch: BatchClaimOutput::from_abi_bytes(&bytes[32..])?,
+        })
+    }
+}
+
+/// Bytes of an image ID as the contract stores it: each word little-endian
+pub fn image_id_bytes(image_id: &[u32; 8]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, word) in image_id.iter().enumerate() {
+        bytes[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocation::EMPTY_BASKET_DIGEST;
+
+    #[test]
+    fn test_batch_output_abi_roundtrip() {
+        let output = BatchClaimOutput {
+            merkle_root: [1u8; 32],
+            epoch_id: 7,
+            campaign_id: [2u8; 32],
+            ownership_verified: true,
+            hash_scheme: HashScheme::Keccak256,
+            exclusion_root: [3u8; 32],
+            policy_commitment: [4u8; 32],
+            chain_id: 1,
+            verifying_contract: [5u8; 20],
+            entries: (1..=3u8)
+                .map(|i| BatchEntry {
+                    nullifier: [i; 32],
+                    recipient: [0xa0 + i; 20],
+                    amount: i as u128 * 1_000,
+                })
+                .collect(),
+        };
+
+        let bytes = output.to_abi_bytes();
+        assert_eq!(
+            bytes.len(),
+            BatchClaimOutput::HEAD_LEN + 3 * BatchEntry::ABI_LEN
+        );
+        assert_eq!(BatchClaimOutput::from_abi_bytes(&bytes), Some(output));
+        assert_eq!(BatchClaimOutput::from_abi_bytes(&bytes[1..]), None);
+    }
+
+    fn claim_output(nullifier: u8) -> ClaimOutput {
+        ClaimOutput {
+            merkle_root: [1u8; 32],
+            nullifier: [nullifier; 32],
+            epoch_id: 1,
+            recipient: [0xa0 + nullifier; 20],
+            campaign_id: [2u8; 32],
+            ownership_verified: true,
+            hash_scheme: HashScheme::Sha256,
+            amount: nullifier as u128,
+            basket_digest: EMPTY_BASKET_DIGEST,
+            timestamp: 0,
+            claimed_amount: 0,
+            next_nullifier: [0u8; 32],
+            min_balance: 0,
+            exclusion_root: [0u8; 32],
+            policy_commitment: [0u8; 32],
+            relayer: [0u8; 20],
+            fee: 0,
+            chain_id: 1,
+            verifying_contract: [3u8; 20],
+            not_before: 0,
+            expires_at: 0,
+            basket: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_from_claims() {
+        let outputs = [claim_output(1), claim_output(2)];
+        let batch = BatchClaimOutput::from_claims(&outputs).unwrap();
+        assert_eq!(batch.merkle_root, [1u8; 32]);
+        assert_eq!(batch.entries[1].recipient, [0xa2; 20]);
+        assert_eq!(batch.entries[1].amount, 2);
+        assert!(BatchClaimOutput::from_claims(&[]).is_none());
+
+        // Repeated nullifiers, other campaigns or deployments and vesting tranches do not fold
+        assert!(BatchClaimOutput::from_claims(&[claim_output(1), claim_output(1)]).is_none());
+        let other_epoch = ClaimOutput {
+            epoch_id: 2,
+            ..claim_output(2)
+        };
+        assert!(BatchClaimOutput::from_claims(&[claim_output(1), other_epoch]).is_none());
+        let other_chain = ClaimOutput {
+            chain_id: 10,
+            ..claim_output(2)
+        };
+        assert!(BatchClaimOutput::from_claims(&[claim_output(1), other_chain]).is_none());
+        let tranche = ClaimOutput {
+            next_nullifier: [9u8; 32],
+            ..claim_output(2)
+        };
+        assert!(BatchClaimOutput::from_claims(&[claim_output(1), tranche]).is_none());
+        let relayed = ClaimOutput {
+            fee: 1,
+            ..claim_output(2)
+        };
+        assert!(BatchClaimOutput::from_claims(&[claim_output(1), relayed]).is_none());
+        let expiring = ClaimOutput {
+            expires_at: 1_700_000_000,
+            ..claim_output(2)
+        };
+        assert!(BatchClaimOutput::from_claims(&[claim_output(1), expiring.clone()]).is_none());
+        assert!(BatchClaimOutput::can_fold(&claim_output(1), &claim_output(2)));
+        assert!(!BatchClaimOutput::can_fold(&claim_output(1), &expiring));
+
+        let aggregate = AggregateClaimOutput {
+            claim_image_id: image_id_bytes(&[1, 2, 3, 4, 5, 6, 7, 8]),
+            batch,
+        };
+        assert_eq!(aggregate.claim_image_id[..8], [1, 0, 0, 0, 2, 0, 0, 0]);
+        let bytes = aggregate.to_abi_bytes();
+        assert_eq!(
+            AggregateClaimOutput::from_abi_bytes(&bytes),
+            Some(aggregate)
+        );
+    }
+}
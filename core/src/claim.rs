@@ -0,0 +1,280 @@
+//! Eligibility checks shared by the single and batch claim guests
+//!
+//! Each check picks the hasher from the campaign's `HashScheme` and returns
+//! `false` rather than panicking, so each guest attaches its own message.
+//! Poseidon trees only verify with the `poseidon` feature enabled.
+
+use crate::ownership::{claim_message, recover_signer, ClaimTerms};
+use crate::sparse::{sparse_key, verify_sparse_non_membership};
+use crate::standard::verify_sorted_merkle_proof;
+#[cfg(feature = "poseidon")]
+use crate::PoseidonHasher;
+use crate::{
+    verify_merkle_proof, ClaimInput, HashScheme, Keccak256Hasher, ListPolicy, MerkleHasher,
+    Sha256Hasher, SparseMerkleProof,
+};
+
+/// Compute the claimer's leaf and check it against `merkle_root`
+pub fn verify_membership(
+    claim_input: &ClaimInput,
+    hash_scheme: HashScheme,
+    merkle_root: &[u8; 32],
+) -> bool {
+    match hash_scheme {
+        HashScheme::Sha256 => verify_tree_membership::<Sha256Hasher>(claim_input, merkle_root),
+        HashScheme::Keccak256 => {
+            verify_tree_membership::<Keccak256Hasher>(claim_input, merkle_root)
+        }
+        #[cfg(feature = "poseidon")]
+        HashScheme::Poseidon => verify_tree_membership::<PoseidonHasher>(claim_input, merkle_root),
+        #[cfg(not(feature = "poseidon"))]
+        HashScheme::Poseidon => false,
+        HashScheme::OpenZeppelinStandard => verify_standard_membership(claim_input, merkle_root),
+    }
+}
+
+/// Check that `address` is not in the exclusion tree with root `exclusion_root`,
+/// hashed like the eligibility tree (Keccak-256 for `OpenZeppelinStandard`)
+pub fn verify_exclusion(
+    address: &[u8; 20],
+    proof: &SparseMerkleProof,
+    hash_scheme: HashScheme,
+    exclusion_root: &[u8; 32],
+) -> bool {
+    let key = sparse_key(address);
+    match hash_scheme {
+        HashScheme::Sha256 => {
+            verify_sparse_non_membership::<Sha256Hasher>(&key, proof, exclusion_root)
+        }
+        HashScheme::Keccak256 | HashScheme::OpenZeppelinStandard => {
+            verify_sparse_non_membership::<Keccak256Hasher>(&key, proof, exclusion_root)
+        }
+        #[cfg(feature = "poseidon")]
+        HashScheme::Poseidon => {
+            verify_sparse_non_membership::<PoseidonHasher>(&key, proof, exclusion_root)
+        }
+        #[cfg(not(feature = "poseidon"))]
+        HashScheme::Poseidon => false,
+    }
+}
+
+/// Check the claimer's memberships in the lists of `policy`
+pub fn verify_list_policy(
+    claim_input: &ClaimInput,
+    hash_scheme: HashScheme,
+    policy: &ListPolicy,
+) -> bool {
+    let address = &claim_input.user_address;
+    let proofs = &claim_input.list_proofs;
+    match hash_scheme {
+        HashScheme::Sha256 => policy.is_satisfied_by(proofs, |proof, root| {
+            proof.verify::<Sha256Hasher>(address, root)
+        }),
+        HashScheme::Keccak256 => policy.is_satisfied_by(proofs, |proof, root| {
+            proof.verify::<Keccak256Hasher>(address, root)
+        }),
+        #[cfg(feature = "poseidon")]
+        HashScheme::Poseidon => policy.is_satisfied_by(proofs, |proof, root| {
+            proof.verify::<PoseidonHasher>(address, root)
+        }),
+        #[cfg(not(feature = "poseidon"))]
+        HashScheme::Poseidon => false,
+        HashScheme::OpenZeppelinStandard => {
+            policy.is_satisfied_by(proofs, |proof, root| proof.verify_standard(address, root))
+        }
+    }
+}
+
+/// Check that the claimer's ownership signature is from the eligible address
+/// and covers `terms`
+pub fn verify_ownership(claim_input: &ClaimInput, terms: &ClaimTerms) -> bool {
+    let Some(signature) = claim_input.ownership_signature.as_deref() else {
+        return false;
+    };
+    let message = claim_message(terms);
+    recover_signer(&message, signature) == Some(claim_input.user_address)
+}
+
+/// Compute the claimer's leaf and check it against `merkle_root` using `H`
+fn verify_tree_membership<H: MerkleHasher>(
+    claim_input: &ClaimInput,
+    merkle_root: &[u8; 32],
+) -> bool {
+    let scheme = claim_input.nullifier_scheme;
+    let secret = claim_input.nullifier_secret.as_ref();
+    let leaf = match claim_input.balance {
+        Some(balance) => {
+            scheme.compute_balance_leaf::<H>(&claim_input.user_address, balance, secret)
+        }
+        None => scheme.compute_leaf::<H>(
+            &claim_input.user_address,
+            claim_input.amount,
+            claim_input.vesting.as_ref(),
+            &claim_input.basket,
+            secret,
+        ),
+    };
+    let Some(leaf) = leaf else {
+        return false;
+    };
+
+    verify_merkle_proof::<H>(
+        &leaf,
+        &claim_input.merkle_proof,
+        claim_input.leaf_index,
+        claim_input.leaf_count,
+        merkle_root,
+    )
+}
+
+/// Check the claimer's leaf against an OpenZeppelin `StandardMerkleTree` root;
+/// sorted-pair proofs carry no index, so `leaf_index` and `leaf_count` are unused.
+fn verify_standard_membership(claim_input: &ClaimInput, merkle_root: &[u8; 32]) -> bool {
+    let scheme = claim_input.nullifier_scheme;
+    let secret = claim_input.nullifier_secret.as_ref();
+    let leaf = match claim_input.balance {
+        Some(balance) => {
+            scheme.compute_standard_balance_leaf(&claim_input.user_address, balance, secret)
+        }
+        None => scheme.compute_standard_leaf(
+            &claim_input.user_address,
+            claim_input.amount,
+            claim_input.vesting.as_ref(),
+            &claim_input.basket,
+            secret,
+        ),
+    };
+
+    leaf.is_some_and(|leaf| {
+        verify_sorted_merkle_proof(&leaf, &claim_input.merkle_proof, merkle_root)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ownership::{private_key_address, sign_personal_message};
+    use crate::standard::{abi_encode_address, hash_sorted_pair, standard_leaf};
+    use crate::{amount_word, commit_root, compute_leaf, hash_pair, NullifierScheme};
+
+    /// Claim for `address` in a two-leaf SHA-256 tree, and the tree's root
+    fn claim(address: [u8; 20]) -> (ClaimInput, [u8; 32]) {
+        let sibling = compute_leaf::<Sha256Hasher>(&[0xee; 20], 5);
+        let leaf = compute_leaf::<Sha256Hasher>(&address, 100);
+        let root = commit_root::<Sha256Hasher>(&hash_pair::<Sha256Hasher>(&leaf, &sibling), 2);
+        let claim_input = ClaimInput {
+            user_address: address,
+            amount: 100,
+            basket: Vec::new(),
+            vesting: None,
+            balance: None,
+            merkle_proof: vec![sibling],
+            leaf_index: 0,
+            leaf_count: 2,
+            epoch_id: 1,
+            nullifier_scheme: NullifierScheme::AddressV1,
+            nullifier_secret: None,
+            ownership_signature: None,
+            exclusion_proof: None,
+            root_index: None,
+            list_proofs: Vec::new(),
+        };
+        (claim_input, root)
+    }
+
+    #[test]
+    fn test_membership_uses_campaign_hasher() {
+        let (claim_input, root) = claim([1u8; 20]);
+        assert!(verify_membership(&claim_input, HashScheme::Sha256, &root));
+        assert!(!verify_membership(
+            &claim_input,
+            HashScheme::Keccak256,
+            &root
+        ));
+
+        // A secret-scheme claim without its secret has no leaf
+        let claim_input = ClaimInput {
+            nullifier_scheme: NullifierScheme::SecretV2,
+            ..claim_input
+        };
+        assert!(!verify_membership(&claim_input, HashScheme::Sha256, &root));
+    }
+
+    #[test]
+    fn test_standard_balance_tree_rejects_allocation_claims() {
+        let (claim_input, _) = claim([1u8; 20]);
+        let scheme = NullifierScheme::AddressV1;
+        let leaf = scheme
+            .compute_standard_balance_leaf(&claim_input.user_address, 500, None)
+            .unwrap();
+        let sibling = standard_leaf(&[abi_encode_address(&[0xee; 20]), amount_word(5)]);
+        let root = hash_sorted_pair(&leaf, &sibling);
+        let balance_claim = ClaimInput {
+            balance: Some(500),
+            merkle_proof: vec![sibling],
+            ..claim_input
+        };
+        let standard = HashScheme::OpenZeppelinStandard;
+        assert!(verify_membership(&balance_claim, standard, &root));
+
+        // Claiming the snapshot balance as an allocation does not verify
+        let allocation_claim = ClaimInput {
+            amount: 500,
+            balance: None,
+            ..balance_claim
+        };
+        assert!(!verify_membership(&allocation_claim, standard, &root));
+    }
+
+    #[test]
+    fn test_ownership_binds_terms() {
+        let key = [9u8; 32];
+        let (mut claim_input, _) = claim(private_key_address(&key).unwrap());
+        let terms = ClaimTerms {
+            campaign_id: [2u8; 32],
+            epoch_id: claim_input.epoch_id,
+            chain_id: 1,
+            verifying_contract: [7u8; 20],
+            recipient: [3u8; 20],
+            relayer: [5u8; 20],
+            fee: 10,
+        };
+        assert!(!verify_ownership(&claim_input, &terms));
+
+        claim_input.ownership_signature = sign_personal_message(&key, &claim_message(&terms));
+        assert!(verify_ownership(&claim_input, &terms));
+
+        // Whoever holds the signature cannot redirect the payout, take it
+        // as another relayer or raise the fee
+        let recipient = [4u8; 20];
+        assert!(!verify_ownership(
+            &claim_input,
+            &ClaimTerms { recipient, ..terms }
+        ));
+        let relayer = [6u8; 20];
+        assert!(!verify_ownership(
+            &claim_input,
+            &ClaimTerms { relayer, ..terms }
+        ));
+        let fee = claim_input.amount;
+        assert!(!verify_ownership(
+            &claim_input,
+            &ClaimTerms { fee, ..terms }
+        ));
+
+        // Nor replay it on another chain or deployment of the campaign
+        let chain_id = 10;
+        assert!(!verify_ownership(
+            &claim_input,
+            &ClaimTerms { chain_id, ..terms }
+        ));
+        let verifying_contract = [8u8; 20];
+        assert!(!verify_ownership(
+            &claim_input,
+            &ClaimTerms {
+                verifying_contract,
+                ..terms
+            }
+        ));
+    }
+}
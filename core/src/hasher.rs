@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::keccak256;
+
+/// Domain tag prepended to every leaf preimage
+pub const LEAF_TAG: u8 = 0x00;
+
+/// Domain tag prepended to every internal node preimage
+pub const NODE_TAG: u8 = 0x01;
+
+/// Domain tag for the final root, which commits to the leaf count
+pub const ROOT_TAG: u8 = 0x02;
+
+/// Hash function used by a tree, committed in the journal
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HashScheme {
+    Sha256 = 0,
+    Keccak256 = 1,
+    Poseidon = 2,
+    /// OpenZeppelin `StandardMerkleTree` (sorted-pair Keccak, no leaf count)
+    OpenZeppelinStandard = 3,
+}
+
+impl HashScheme {
+    /// Decode the journal byte
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HashScheme::Sha256),
+            1 => Some(HashScheme::Keccak256),
+            2 => Some(HashScheme::Poseidon),
+            3 => Some(HashScheme::OpenZeppelinStandard),
+            _ => None,
+        }
+    }
+}
+
+/// Hash backend for the Merkle primitives
+///
+/// Byte-oriented hashers only implement `digest`; the default methods apply
+/// the leaf/node/root domain tags. Hashers over a field override them.
+pub trait MerkleHasher {
+    /// Identifier committed in the journal
+    const SCHEME: HashScheme;
+
+    /// Hash the concatenation of `parts`
+    fn digest(parts: &[&[u8]]) -> [u8; 32];
+
+    /// Hash a leaf preimage
+    fn hash_leaf(data: &[u8]) -> [u8; 32] {
+        Self::digest(&[&[LEAF_TAG], data])
+    }
+
+    /// Hash two child nodes
+    fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        Self::digest(&[&[NODE_TAG], left, right])
+    }
+
+    /// Commit the leaf count into the top node of the padded tree
+    fn hash_root(top: &[u8; 32], leaf_count: u32) -> [u8; 32] {
+        Self::digest(&[&[ROOT_TAG], &leaf_count.to_be_bytes(), top])
+    }
+}
+
+/// SHA-256 (accelerated in the zkVM)
+pub struct Sha256Hasher;
+
+impl MerkleHasher for Sha256Hasher {
+    const SCHEME: HashScheme = HashScheme::Sha256;
+
+    fn digest(parts: &[&[u8]]) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update(part);
+        }
+        let result = hasher.finalize();
+        let mut output = [0u8; 32];
+        output.copy_from_slice(&result);
+        output
+    }
+}
+
+/// Ethereum Keccak-256
+pub struct Keccak256Hasher;
+
+impl MerkleHasher for Keccak256Hasher {
+    const SCHEME: HashScheme = HashScheme::Keccak256;
+
+    fn digest(parts: &[&[u8]]) -> [u8; 32] {
+        keccak256(&parts.concat())
+    }
+}
@@ -1,83 +1,525 @@
 use serde::{Deserialize, Serialize};
 
+pub mod allocation;
+pub mod batch;
+pub mod claim;
+pub mod hasher;
+pub mod ownership;
+pub mod policy;
+#[cfg(feature = "poseidon")]
+pub mod poseidon;
+pub mod sparse;
+pub mod standard;
+pub mod tier;
+pub mod vesting;
+
+pub use allocation::{AllocationRecord, TokenAllocation};
+pub use batch::{AggregateClaimOutput, BatchClaim, BatchClaimOutput, BatchEntry};
+pub use hasher::{
+    HashScheme, Keccak256Hasher, MerkleHasher, Sha256Hasher, LEAF_TAG, NODE_TAG, ROOT_TAG,
+};
+pub use policy::{ListPolicy, ListProof};
+#[cfg(feature = "poseidon")]
+pub use poseidon::PoseidonHasher;
+pub use sparse::SparseMerkleProof;
+pub use tier::{TierClaimOutput, TierTable};
+pub use vesting::VestingSchedule;
+
 /// Input data for a claim proof
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ClaimInput {
     /// User's address (20 bytes for Ethereum address)
     pub user_address: [u8; 20],
+    /// Allocation for `user_address`, committed in its leaf
+    pub amount: u128,
+    /// Additional tokens allocated to `user_address`; empty for single-amount campaigns
+    pub basket: Vec<TokenAllocation>,
+    /// Vesting schedule over `amount`, committed in the leaf; `None` if fully unlocked
+    pub vesting: Option<VestingSchedule>,
+    /// Snapshot balance for threshold campaigns; the leaf is then
+    /// `compute_balance_leaf` and the balance never leaves the guest
+    pub balance: Option<u128>,
     /// Merkle proof path (array of 32-byte hashes)
     pub merkle_proof: Vec<[u8; 32]>,
     /// Position of the leaf in the tree
     pub leaf_index: u32,
+    /// Number of leaves in the tree (committed into the root)
+    pub leaf_count: u32,
     /// Epoch identifier for this airdrop round
     pub epoch_id: u64,
+    /// How the nullifier is derived (and which leaf format the tree uses)
+    pub nullifier_scheme: NullifierScheme,
+    /// User-held secret for `NullifierScheme::SecretV2`; never leaves the guest
+    pub nullifier_secret: Option<[u8; 32]>,
+    /// 65-byte `personal_sign` signature by `user_address` over
+    /// `ownership::claim_message`, required when `PublicInputs::require_ownership` is set
+    pub ownership_signature: Option<Vec<u8>>,
+    /// Non-membership proof of `sparse::sparse_key(user_address)` in the
+    /// exclusion tree, required when `PublicInputs::exclusion_root` is set
+    pub exclusion_proof: Option<SparseMerkleProof>,
+    /// Position of the proven root in `PublicInputs::root_set`; never revealed
+    pub root_index: Option<u32>,
+    /// Memberships in the lists of `PublicInputs::list_policy`, by ascending list
+    pub list_proofs: Vec<ListProof>,
+}
+
+/// Versioned nullifier derivation
+///
+/// The scheme also fixes the leaf format, so a tree is built for exactly one
+/// scheme and a claimer cannot pick a different one for the same leaf.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NullifierScheme {
+    /// `sha256(address || epoch_le)` over `(address, amount)` leaves.
+    /// Anyone holding the eligibility list can link nullifiers to addresses.
+    AddressV1,
+    /// `sha256(tag || secret || epoch_le)` over leaves that commit to the secret
+    SecretV2,
+}
+
+impl NullifierScheme {
+    /// Compute the leaf for this scheme, or `None` if the secret is missing or
+    /// the basket repeats a token
+    pub fn compute_leaf<H: MerkleHasher>(
+        &self,
+        address: &[u8; 20],
+        amount: u128,
+        vesting: Option<&VestingSchedule>,
+        basket: &[TokenAllocation],
+        secret: Option<&[u8; 32]>,
+    ) -> Option<[u8; 32]> {
+        let commitment = self.commitment(secret)?;
+        compute_allocation_leaf::<H>(address, amount, vesting, basket, commitment.as_ref())
+    }
+
+    /// Compute the `(address, balance)` leaf of a balance snapshot for this
+    /// scheme, or `None` if the secret is missing
+    pub fn compute_balance_leaf<H: MerkleHasher>(
+        &self,
+        address: &[u8; 20],
+        balance: u128,
+        secret: Option<&[u8; 32]>,
+    ) -> Option<[u8; 32]> {
+        let commitment = self.commitment(secret)?;
+        Some(compute_balance_leaf::<H>(
+            address,
+            balance,
+            commitment.as_ref(),
+        ))
+    }
+
+    /// Compute the OpenZeppelin standard balance snapshot leaf for this scheme:
+    /// `["bytes32", "address", "uint256"]` led by `standard::balance_tag_word`,
+    /// with a `bytes32` commitment for `SecretV2`, or `None` if the secret is missing
+    pub fn compute_standard_balance_leaf(
+        &self,
+        address: &[u8; 20],
+        balance: u128,
+        secret: Option<&[u8; 32]>,
+    ) -> Option<[u8; 32]> {
+        let mut words = vec![
+            standard::balance_tag_word(),
+            standard::abi_encode_address(address),
+            amount_word(balance),
+        ];
+        words.extend(self.commitment(secret)?);
+        Some(standard::standard_leaf(&words))
+    }
+
+    /// Compute the OpenZeppelin standard leaf for this scheme: `["address", "uint256"]`,
+    /// followed by `uint64` start, cliff and duration for a vesting leaf, a `bytes32`
+    /// basket digest if the basket is not empty and a `bytes32` commitment for `SecretV2`
+    pub fn compute_standard_leaf(
+        &self,
+        address: &[u8; 20],
+        amount: u128,
+        vesting: Option<&VestingSchedule>,
+        basket: &[TokenAllocation],
+        secret: Option<&[u8; 32]>,
+    ) -> Option<[u8; 32]> {
+        let mut words = vec![standard::abi_encode_address(address), amount_word(amount)];
+        if let Some(vesting) = vesting {
+            for value in [vesting.start, vesting.cliff, vesting.duration] {
+                words.push(amount_word(value as u128));
+            }
+        }
+        if !basket.is_empty() {
+            words.push(allocation::basket_digest(basket)?);
+        }
+        words.extend(self.commitment(secret)?);
+        Some(standard::standard_leaf(&words))
+    }
+
+    /// Secret commitment bound into the leaf: `Some(None)` for `AddressV1`,
+    /// `None` if `SecretV2` is missing its secret
+    fn commitment(&self, secret: Option<&[u8; 32]>) -> Option<Option<[u8; 32]>> {
+        match self {
+            NullifierScheme::AddressV1 => Some(None),
+            NullifierScheme::SecretV2 => Some(Some(compute_secret_commitment(secret?))),
+        }
+    }
+
+    /// Compute the nullifier for this scheme, or `None` if the secret is missing
+    pub fn compute_nullifier(
+        &self,
+        address: &[u8; 20],
+        secret: Option<&[u8; 32]>,
+        epoch_id: u64,
+    ) -> Option<[u8; 32]> {
+        match self {
+            NullifierScheme::AddressV1 => Some(compute_nullifier(address, epoch_id)),
+            NullifierScheme::SecretV2 => Some(compute_secret_nullifier(secret?, epoch_id)),
+        }
+    }
 }
 
 /// Output data committed to the journal
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ClaimOutput {
-    /// The verified Merkle root
+    /// The verified Merkle root, or the `root_set_commitment` of the accepted roots
     pub merkle_root: [u8; 32],
     /// Nullifier to prevent double-claiming
     pub nullifier: [u8; 32],
     /// Epoch ID that was verified
     pub epoch_id: u64,
+    /// Address that receives the reward
+    pub recipient: [u8; 20],
+    /// Campaign the claim was made for
+    pub campaign_id: [u8; 32],
+    /// Whether the claimer proved control of the eligible address
+    pub ownership_verified: bool,
+    /// Hash function the Merkle proof was verified with
+    pub hash_scheme: HashScheme,
+    /// Allocation proven by the leaf, paid out by the contract
+    pub amount: u128,
+    /// `allocation::basket_digest` of `basket`
+    pub basket_digest: [u8; 32],
+    /// Timestamp vesting was evaluated at
+    pub timestamp: u64,
+    /// Amount paid out before this tranche
+    pub claimed_amount: u128,
+    /// Nullifier of the tranche after this one; zero without vesting
+    pub next_nullifier: [u8; 32],
+    /// Balance threshold the claimer proved to meet; zero outside threshold campaigns
+    pub min_balance: u128,
+    /// Exclusion tree the claimer proved not to be in; zero without an exclusion list
+    pub exclusion_root: [u8; 32],
+    /// `ListPolicy::commitment` of the policy the claimer satisfied; zero without one
+    pub policy_commitment: [u8; 32],
+    /// Relayer paid `fee`; zero lets whoever submits the claim take it
+    pub relayer: [u8; 20],
+    /// Part of the payout that goes to the relayer instead of `recipient`
+    pub fee: u128,
+    /// Chain the claim is valid on
+    pub chain_id: u64,
+    /// `ZKAirdrop` deployment the claim is valid for
+    pub verifying_contract: [u8; 20],
+    /// Unix time before which the claim cannot be submitted; zero without a lower bound
+    pub not_before: u64,
+    /// Unix time after which the claim can no longer be submitted; zero if it never expires
+    pub expires_at: u64,
+    /// Token basket proven by the leaf, in canonical order
+    pub basket: Vec<TokenAllocation>,
+}
+
+impl ClaimOutput {
+    /// Length of the fixed part of the packed journal; basket entries follow it
+    pub const HEAD_LEN: usize = 454;
+
+    /// Encode as `abi.encodePacked(merkleRoot, nullifier, uint64(epochId), recipient,
+    /// campaignId, ownershipVerified, uint8(hashScheme), uint256(amount), basketDigest,
+    /// uint64(timestamp), uint256(claimedAmount), nextNullifier, uint256(minBalance),
+    /// exclusionRoot, policyCommitment, relayer, uint256(fee), uint64(chainId), verifyingContract,
+    /// uint64(notBefore), uint64(expiresAt))` followed by `abi.encodePacked(token, uint256(amount))` for each basket entry
+    pub fn to_abi_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(Self::HEAD_LEN + self.basket.len() * allocation::BASKET_ENTRY_LEN);
+        bytes.extend_from_slice(&self.merkle_root);
+        bytes.extend_from_slice(&self.nullifier);
+        bytes.extend_from_slice(&self.epoch_id.to_be_bytes());
+        bytes.extend_from_slice(&self.recipient);
+        bytes.extend_from_slice(&self.campaign_id);
+        bytes.push(self.ownership_verified as u8);
+        bytes.push(self.hash_scheme as u8);
+        bytes.extend_from_slice(&amount_word(self.amount));
+        bytes.extend_from_slice(&self.basket_digest);
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&amount_word(self.claimed_amount));
+        bytes.extend_from_slice(&self.next_nullifier);
+        bytes.extend_from_slice(&amount_word(self.min_balance));
+        bytes.extend_from_slice(&self.exclusion_root);
+        bytes.extend_from_slice(&self.policy_commitment);
+        bytes.extend_from_slice(&self.relayer);
+        bytes.extend_from_slice(&amount_word(self.fee));
+        bytes.extend_from_slice(&self.chain_id.to_be_bytes());
+        bytes.extend_from_slice(&self.verifying_contract);
+        bytes.extend_from_slice(&self.not_before.to_be_bytes());
+        bytes.extend_from_slice(&self.expires_at.to_be_bytes());
+        for entry in &self.basket {
+            bytes.extend_from_slice(&entry.to_abi_bytes());
+        }
+        bytes
+    }
+
+    /// Decode the packed journal layout, returning `None` on a length mismatch
+    /// or a basket that is not canonical or does not match its digest
+    pub fn from_abi_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::HEAD_LEN
+            || !(bytes.len() - Self::HEAD_LEN).is_multiple_of(allocation::BASKET_ENTRY_LEN)
+        {
+            return None;
+        }
+
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[0..32]);
+        let mut nullifier = [0u8; 32];
+        nullifier.copy_from_slice(&bytes[32..64]);
+        let mut epoch_bytes = [0u8; 8];
+        epoch_bytes.copy_from_slice(&bytes[64..72]);
+        let mut recipient = [0u8; 20];
+        recipient.copy_from_slice(&bytes[72..92]);
+        let mut campaign_id = [0u8; 32];
+        campaign_id.copy_from_slice(&bytes[92..124]);
+        let ownership_verified = match bytes[124] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let hash_scheme = HashScheme::from_u8(bytes[125])?;
+        let amount = decode_amount(&bytes[126..158])?;
+        let mut basket_digest = [0u8; 32];
+        basket_digest.copy_from_slice(&bytes[158..190]);
+        let mut timestamp_bytes = [0u8; 8];
+        timestamp_bytes.copy_from_slice(&bytes[190..198]);
+        let claimed_amount = decode_amount(&bytes[198..230])?;
+        let mut next_nullifier = [0u8; 32];
+        next_nullifier.copy_from_slice(&bytes[230..262]);
+        let min_balance = decode_amount(&bytes[262..294])?;
+        let mut exclusion_root = [0u8; 32];
+        exclusion_root.copy_from_slice(&bytes[294..326]);
+        let mut policy_commitment = [0u8; 32];
+        policy_commitment.copy_from_slice(&bytes[326..358]);
+        let mut relayer = [0u8; 20];
+        relayer.copy_from_slice(&bytes[358..378]);
+        let fee = decode_amount(&bytes[378..410])?;
+        let mut chain_id_bytes = [0u8; 8];
+        chain_id_bytes.copy_from_slice(&bytes[410..418]);
+        let mut verifying_contract = [0u8; 20];
+        verifying_contract.copy_from_slice(&bytes[418..438]);
+        let mut not_before_bytes = [0u8; 8];
+        not_before_bytes.copy_from_slice(&bytes[438..446]);
+        let mut expires_at_bytes = [0u8; 8];
+        expires_at_bytes.copy_from_slice(&bytes[446..454]);
+        let basket = bytes[Self::HEAD_LEN..]
+            .chunks(allocation::BASKET_ENTRY_LEN)
+            .map(TokenAllocation::from_abi_bytes)
+            .collect::<Option<Vec<_>>>()?;
+        if !allocation::is_canonical(&basket)
+            || allocation::basket_digest(&basket)? != basket_digest
+        {
+            return None;
+        }
+
+        Some(ClaimOutput {
+            merkle_root,
+            nullifier,
+            epoch_id: u64::from_be_bytes(epoch_bytes),
+            recipient,
+            campaign_id,
+            ownership_verified,
+            hash_scheme,
+            amount,
+            basket_digest,
+            timestamp: u64::from_be_bytes(timestamp_bytes),
+            claimed_amount,
+            next_nullifier,
+            min_balance,
+            exclusion_root,
+            policy_commitment,
+            relayer,
+            fee,
+            chain_id: u64::from_be_bytes(chain_id_bytes),
+            verifying_contract,
+            not_before: u64::from_be_bytes(not_before_bytes),
+            expires_at: u64::from_be_bytes(expires_at_bytes),
+            basket,
+        })
+    }
+}
+
+/// Decode a `uint256` word holding a `u128`, rejecting larger values
+fn decode_amount(word: &[u8]) -> Option<u128> {
+    if word[..16].iter().any(|&byte| byte != 0) {
+        return None;
+    }
+    let mut amount_bytes = [0u8; 16];
+    amount_bytes.copy_from_slice(&word[16..]);
+    Some(u128::from_be_bytes(amount_bytes))
 }
 
 /// Public inputs that will be committed to the journal
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PublicInputs {
-    /// The expected Merkle root
+    /// The expected Merkle root, or the `root_set_commitment` of `root_set`
     pub merkle_root: [u8; 32],
     /// Current epoch ID
     pub epoch_id: u64,
+    /// Payout address chosen by the claimer; may differ from the eligible address
+    pub recipient: [u8; 20],
+    /// Campaign identifier included in the signed claim message
+    pub campaign_id: [u8; 32],
+    /// Chain of the deployment the proof is for; with `verifying_contract` and
+    /// `campaign_id` it is mixed into the nullifier, signed and committed
+    pub chain_id: u64,
+    /// Address of the `ZKAirdrop` deployment the proof is for
+    pub verifying_contract: [u8; 20],
+    /// Require a signature from the eligible address (see `ownership`)
+    pub require_ownership: bool,
+    /// Hash function the tree was built with
+    pub hash_scheme: HashScheme,
+    /// Unix timestamp vesting is evaluated at; must not be ahead of the chain
+    pub timestamp: u64,
+    /// Amount already paid out for this allocation; the tranche being claimed
+    /// starts here (always 0 without vesting)
+    pub claimed_amount: u128,
+    /// Minimum snapshot balance for threshold campaigns, proven against
+    /// `ClaimInput::balance` without revealing it
+    pub min_balance: Option<u128>,
+    /// Tier table for tiered campaigns; the guest then commits a
+    /// `TierClaimOutput` with the tier of `ClaimInput::balance`
+    pub tiers: Option<TierTable>,
+    /// Root of a `sparse` exclusion tree the claimer must prove not to be in,
+    /// hashed like the eligibility tree (Keccak-256 for `OpenZeppelinStandard`)
+    pub exclusion_root: Option<[u8; 32]>,
+    /// Accepted eligibility roots; the claimer proves membership in one of them
+    /// without revealing which, and only their commitment is committed
+    pub root_set: Option<Vec<[u8; 32]>>,
+    /// Additional lists the eligible address must be on, e.g. NFT holders
+    /// on top of the allocation list
+    pub list_policy: Option<ListPolicy>,
+    /// Relayer that submits the claim and is paid `fee`; zero for any submitter
+    pub relayer: [u8; 20],
+    /// Cut of the payout committed to the relayer; zero when the claimer submits
+    pub fee: u128,
+    /// Earliest Unix time the claim may be submitted; `None` for no lower bound
+    pub not_before: Option<u64>,
+    /// Latest Unix time the claim may be submitted, e.g. an hour after a
+    /// relayer proves it; `None` if it never expires
+    pub expires_at: Option<u64>,
 }
 
-/// Compute a leaf hash from an Ethereum address
-pub fn compute_leaf(address: &[u8; 20]) -> [u8; 32] {
-    use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    hasher.update(address);
-    let result = hasher.finalize();
-    let mut output = [0u8; 32];
-    output.copy_from_slice(&result);
-    output
+/// Hash used for padding leaves; the leaf level is padded with it up to the
+/// next power of two. It is never a valid leaf since proofs require
+/// `index < leaf_count`.
+pub const EMPTY_LEAF: [u8; 32] = [0u8; 32];
+
+/// Prefix of balance snapshot leaves, so they never verify as allocation leaves
+pub(crate) const BALANCE_LEAF_TAG: &[u8] = b"zkairdrop.balance.v1";
+
+/// Encode an amount as a big-endian `uint256` word
+pub fn amount_word(amount: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&amount.to_be_bytes());
+    word
+}
+
+/// Compute a leaf hash from an Ethereum address and its allocation
+pub fn compute_leaf<H: MerkleHasher>(address: &[u8; 20], amount: u128) -> [u8; 32] {
+    H::hash_leaf(&[address.as_slice(), &amount_word(amount)].concat())
+}
+
+/// Compute a leaf hash that binds an address and allocation to a secret commitment
+pub fn compute_committed_leaf<H: MerkleHasher>(
+    address: &[u8; 20],
+    amount: u128,
+    commitment: &[u8; 32],
+) -> [u8; 32] {
+    H::hash_leaf(&[address.as_slice(), &amount_word(amount), commitment].concat())
+}
+
+/// Compute a leaf hash for an address, its amount, an optional vesting schedule
+/// and a token basket, bound to a secret commitment if one is given
+///
+/// The schedule and basket digest are only appended when present, so
+/// single-amount leaves match `compute_leaf` and `compute_committed_leaf`.
+/// Returns `None` if the basket repeats a token.
+pub fn compute_allocation_leaf<H: MerkleHasher>(
+    address: &[u8; 20],
+    amount: u128,
+    vesting: Option<&VestingSchedule>,
+    basket: &[TokenAllocation],
+    commitment: Option<&[u8; 32]>,
+) -> Option<[u8; 32]> {
+    let mut preimage = [address.as_slice(), &amount_word(amount)].concat();
+    if let Some(vesting) = vesting {
+        preimage.extend_from_slice(&vesting.to_bytes());
+    }
+    if !basket.is_empty() {
+        preimage.extend_from_slice(&allocation::basket_digest(basket)?);
+    }
+    if let Some(commitment) = commitment {
+        preimage.extend_from_slice(commitment);
+    }
+    Some(H::hash_leaf(&preimage))
+}
+
+/// Compute a balance snapshot leaf, bound to a secret commitment if one is given
+pub fn compute_balance_leaf<H: MerkleHasher>(
+    address: &[u8; 20],
+    balance: u128,
+    commitment: Option<&[u8; 32]>,
+) -> [u8; 32] {
+    let mut preimage = [BALANCE_LEAF_TAG, address.as_slice(), &amount_word(balance)].concat();
+    if let Some(commitment) = commitment {
+        preimage.extend_from_slice(commitment);
+    }
+    H::hash_leaf(&preimage)
 }
 
 /// Compute intermediate hash for Merkle tree
-pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-    use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    hasher.update(left);
-    hasher.update(right);
-    let result = hasher.finalize();
-    let mut output = [0u8; 32];
-    output.copy_from_slice(&result);
-    output
+pub fn hash_pair<H: MerkleHasher>(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    H::hash_node(left, right)
+}
+
+/// Commit the leaf count into the top node of the padded tree
+pub fn commit_root<H: MerkleHasher>(top: &[u8; 32], leaf_count: u32) -> [u8; 32] {
+    H::hash_root(top, leaf_count)
+}
+
+/// Depth of a tree with `leaf_count` leaves padded to a power of two
+pub fn tree_depth(leaf_count: u32) -> usize {
+    leaf_count.max(1).next_power_of_two().trailing_zeros() as usize
 }
 
 /// Verify a Merkle proof
-pub fn verify_merkle_proof(
+///
+/// The proof must have exactly `tree_depth(leaf_count)` siblings and the
+/// index must address a real (non-padding) leaf.
+pub fn verify_merkle_proof<H: MerkleHasher>(
     leaf: &[u8; 32],
     proof: &[[u8; 32]],
     index: u32,
+    leaf_count: u32,
     root: &[u8; 32],
 ) -> bool {
+    if index >= leaf_count || proof.len() != tree_depth(leaf_count) {
+        return false;
+    }
+
     let mut computed_hash = *leaf;
     let mut current_index = index;
 
     for proof_element in proof {
-        if current_index % 2 == 0 {
+        if current_index.is_multiple_of(2) {
             // Current node is left child
-            computed_hash = hash_pair(&computed_hash, proof_element);
+            computed_hash = hash_pair::<H>(&computed_hash, proof_element);
         } else {
             // Current node is right child
-            computed_hash = hash_pair(proof_element, &computed_hash);
+            computed_hash = hash_pair::<H>(proof_element, &computed_hash);
         }
         current_index /= 2;
     }
 
-    computed_hash == *root
+    commit_root::<H>(&computed_hash, leaf_count) == *root
 }
 
 /// Compute nullifier from address and epoch
@@ -85,7 +527,98 @@ pub fn compute_nullifier(address: &[u8; 20], epoch_id: u64) -> [u8; 32] {
     use sha2::{Digest, Sha256};
     let mut hasher = Sha256::new();
     hasher.update(address);
-    hasher.update(&epoch_id.to_le_bytes());
+    hasher.update(epoch_id.to_le_bytes());
+    let result = hasher.finalize();
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&result);
+    output
+}
+
+/// Commitment to a set of accepted roots: `keccak256(abi.encodePacked(bytes32[] roots))`
+pub fn root_set_commitment(roots: &[[u8; 32]]) -> [u8; 32] {
+    keccak256(roots.as_flattened())
+}
+
+/// Compute the Ethereum Keccak-256 hash of `data`
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    const RATE: usize = 136;
+
+    fn absorb(state: &mut [u64; 25], block: &[u8; RATE]) {
+        let (words, _) = block.as_chunks::<8>();
+        for (lane, word) in state.iter_mut().zip(words) {
+            *lane ^= u64::from_le_bytes(*word);
+        }
+        keccak::f1600(state);
+    }
+
+    let mut state = [0u64; 25];
+    let (blocks, remainder) = data.as_chunks::<RATE>();
+    for block in blocks {
+        absorb(&mut state, block);
+    }
+
+    // Original Keccak padding (0x01 ... 0x80), not the SHA-3 0x06 variant
+    let mut last = [0u8; RATE];
+    last[..remainder.len()].copy_from_slice(remainder);
+    last[remainder.len()] ^= 0x01;
+    last[RATE - 1] ^= 0x80;
+    absorb(&mut state, &last);
+
+    let mut output = [0u8; 32];
+    for (chunk, lane) in output.as_chunks_mut::<8>().0.iter_mut().zip(state.iter()) {
+        *chunk = lane.to_le_bytes();
+    }
+    output
+}
+
+/// Domain tag for secret commitments published in the tree
+const SECRET_COMMITMENT_TAG: &[u8] = b"zkairdrop.secret.v2";
+
+/// Domain tag for secret-keyed nullifiers
+const SECRET_NULLIFIER_TAG: &[u8] = b"zkairdrop.nullifier.v2";
+
+/// Domain tag for nullifiers bound to a deployment
+const DOMAIN_NULLIFIER_TAG: &[u8] = b"zkairdrop.domain.v1";
+
+/// Compute the public commitment to a nullifier secret
+pub fn compute_secret_commitment(secret: &[u8; 32]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(SECRET_COMMITMENT_TAG);
+    hasher.update(secret);
+    let result = hasher.finalize();
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&result);
+    output
+}
+
+/// Compute nullifier from a user-held secret and epoch
+pub fn compute_secret_nullifier(secret: &[u8; 32], epoch_id: u64) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(SECRET_NULLIFIER_TAG);
+    hasher.update(secret);
+    hasher.update(epoch_id.to_le_bytes());
+    let result = hasher.finalize();
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&result);
+    output
+}
+
+/// Bind a nullifier to one deployment: the chain, the verifying contract and the campaign
+pub fn compute_domain_nullifier(
+    nullifier: &[u8; 32],
+    chain_id: u64,
+    verifying_contract: &[u8; 20],
+    campaign_id: &[u8; 32],
+) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(DOMAIN_NULLIFIER_TAG);
+    hasher.update(nullifier);
+    hasher.update(chain_id.to_be_bytes());
+    hasher.update(verifying_contract);
+    hasher.update(campaign_id);
     let result = hasher.finalize();
     let mut output = [0u8; 32];
     output.copy_from_slice(&result);
@@ -100,10 +633,62 @@ mod tests {
     fn test_hash_pair() {
         let left = [1u8; 32];
         let right = [2u8; 32];
-        let hash = hash_pair(&left, &right);
+        let hash = hash_pair::<Sha256Hasher>(&left, &right);
         assert_ne!(hash, [0u8; 32]);
     }
 
+    #[test]
+    fn test_domain_separation() {
+        use sha2::{Digest, Sha256};
+
+        let left = [1u8; 32];
+        let right = [2u8; 32];
+
+        // An internal node preimage does not hash to the same value as a leaf
+        let mut preimage = [0u8; 64];
+        preimage[..32].copy_from_slice(&left);
+        preimage[32..].copy_from_slice(&right);
+        let untagged: [u8; 32] = Sha256::digest(preimage).into();
+        assert_ne!(hash_pair::<Sha256Hasher>(&left, &right), untagged);
+
+        // The root commits to the leaf count
+        assert_ne!(
+            commit_root::<Sha256Hasher>(&left, 3),
+            commit_root::<Sha256Hasher>(&left, 4)
+        );
+
+        // Keccak uses the same tags
+        let tagged = keccak256(&[&[NODE_TAG], left.as_slice(), &right].concat());
+        assert_eq!(hash_pair::<Keccak256Hasher>(&left, &right), tagged);
+        assert_ne!(
+            hash_pair::<Keccak256Hasher>(&left, &right),
+            hash_pair::<Sha256Hasher>(&left, &right)
+        );
+    }
+
+    #[test]
+    fn test_tree_depth() {
+        assert_eq!(tree_depth(1), 0);
+        assert_eq!(tree_depth(2), 1);
+        assert_eq!(tree_depth(3), 2);
+        assert_eq!(tree_depth(4), 2);
+        assert_eq!(tree_depth(5), 3);
+    }
+
+    #[test]
+    fn test_verify_rejects_padding_and_short_proofs() {
+        // Two-leaf tree: root = commit(hash_pair::<Sha256Hasher>(a, b), 2)
+        let a = compute_leaf::<Sha256Hasher>(&[1u8; 20], 100);
+        let b = compute_leaf::<Sha256Hasher>(&[2u8; 20], 100);
+        let root = commit_root::<Sha256Hasher>(&hash_pair::<Sha256Hasher>(&a, &b), 2);
+
+        assert!(verify_merkle_proof::<Sha256Hasher>(&a, &[b], 0, 2, &root));
+        assert!(verify_merkle_proof::<Sha256Hasher>(&b, &[a], 1, 2, &root));
+        assert!(!verify_merkle_proof::<Sha256Hasher>(&a, &[b], 2, 2, &root));
+        assert!(!verify_merkle_proof::<Sha256Hasher>(&a, &[], 0, 2, &root));
+        assert!(!verify_merkle_proof::<Sha256Hasher>(&a, &[b], 0, 3, &root));
+    }
+
     #[test]
     fn test_compute_nullifier() {
         let address = [1u8; 20];
@@ -119,4 +704,325 @@ mod tests {
         let nullifier3 = compute_nullifier(&address, 2u64);
         assert_ne!(nullifier, nullifier3);
     }
+
+    #[test]
+    fn test_domain_nullifier() {
+        let nullifier = compute_nullifier(&[1u8; 20], 1);
+        let contract = [2u8; 20];
+        let campaign_id = [3u8; 32];
+        let bound = compute_domain_nullifier(&nullifier, 1, &contract, &campaign_id);
+        assert_ne!(bound, nullifier);
+
+        // Another chain, deployment or campaign gives an unrelated nullifier
+        assert_ne!(
+            bound,
+            compute_domain_nullifier(&nullifier, 10, &contract, &campaign_id)
+        );
+        assert_ne!(
+            bound,
+            compute_domain_nullifier(&nullifier, 1, &[4u8; 20], &campaign_id)
+        );
+        assert_ne!(
+            bound,
+            compute_domain_nullifier(&nullifier, 1, &contract, &[5u8; 32])
+        );
+    }
+
+    #[test]
+    fn test_secret_nullifier_scheme() {
+        let address = [1u8; 20];
+        let secret = [9u8; 32];
+        let scheme = NullifierScheme::SecretV2;
+
+        // Leaf is bound to the secret through its commitment
+        let leaf = scheme
+            .compute_leaf::<Sha256Hasher>(&address, 100, None, &[], Some(&secret))
+            .unwrap();
+        assert_eq!(
+            leaf,
+            compute_committed_leaf::<Sha256Hasher>(
+                &address,
+                100,
+                &compute_secret_commitment(&secret)
+            )
+        );
+        assert_ne!(
+            leaf,
+            scheme
+                .compute_leaf::<Sha256Hasher>(&address, 100, None, &[], Some(&[8u8; 32]))
+                .unwrap()
+        );
+        assert_eq!(
+            scheme.compute_leaf::<Sha256Hasher>(&address, 100, None, &[], None),
+            None
+        );
+
+        // Nullifier no longer depends on the address
+        let nullifier = scheme
+            .compute_nullifier(&address, Some(&secret), 1)
+            .unwrap();
+        assert_eq!(nullifier, compute_secret_nullifier(&secret, 1));
+        assert_ne!(nullifier, compute_nullifier(&address, 1));
+        assert_ne!(nullifier, compute_secret_nullifier(&secret, 2));
+    }
+
+    #[test]
+    fn test_standard_leaf_scheme() {
+        let address = [1u8; 20];
+        let secret = [9u8; 32];
+        let address_word = standard::abi_encode_address(&address);
+
+        assert_eq!(
+            NullifierScheme::AddressV1.compute_standard_leaf(&address, 100, None, &[], None),
+            Some(standard::standard_leaf(&[address_word, amount_word(100)]))
+        );
+        assert_eq!(
+            NullifierScheme::SecretV2.compute_standard_leaf(
+                &address,
+                100,
+                None,
+                &[],
+                Some(&secret)
+            ),
+            Some(standard::standard_leaf(&[
+                address_word,
+                amount_word(100),
+                compute_secret_commitment(&secret)
+            ]))
+        );
+        assert_eq!(
+            NullifierScheme::SecretV2.compute_standard_leaf(&address, 100, None, &[], None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_balance_leaf() {
+        let address = [1u8; 20];
+
+        // A balance snapshot leaf is not an allocation leaf for the same value
+        assert_ne!(
+            compute_balance_leaf::<Sha256Hasher>(&address, 500, None),
+            compute_leaf::<Sha256Hasher>(&address, 500)
+        );
+        assert_ne!(
+            compute_balance_leaf::<Sha256Hasher>(&address, 500, None),
+            compute_balance_leaf::<Sha256Hasher>(&address, 501, None)
+        );
+
+        let secret = [9u8; 32];
+        assert_eq!(
+            NullifierScheme::SecretV2.compute_balance_leaf::<Sha256Hasher>(
+                &address,
+                500,
+                Some(&secret)
+            ),
+            Some(compute_balance_leaf::<Sha256Hasher>(
+                &address,
+                500,
+                Some(&compute_secret_commitment(&secret))
+            ))
+        );
+
+        // The same holds for OpenZeppelin standard leaves
+        assert_ne!(
+            NullifierScheme::AddressV1.compute_standard_balance_leaf(&address, 500, None),
+            NullifierScheme::AddressV1.compute_standard_leaf(&address, 500, None, &[], None)
+        );
+    }
+
+    #[test]
+    fn test_address_nullifier_scheme() {
+        let address = [1u8; 20];
+        let scheme = NullifierScheme::AddressV1;
+        assert_eq!(
+            scheme.compute_leaf::<Sha256Hasher>(&address, 100, None, &[], None),
+            Some(compute_leaf::<Sha256Hasher>(&address, 100))
+        );
+
+        // The allocation is part of the leaf
+        assert_ne!(
+            compute_leaf::<Sha256Hasher>(&address, 100),
+            compute_leaf::<Sha256Hasher>(&address, 101)
+        );
+        assert_eq!(
+            scheme.compute_nullifier(&address, None, 1),
+            Some(compute_nullifier(&address, 1))
+        );
+    }
+
+    #[test]
+    fn test_keccak256() {
+        let empty = keccak256(b"");
+        assert_eq!(&empty[..4], &[0xc5, 0xd2, 0x46, 0x01]);
+        assert_eq!(&empty[28..], &[0x5d, 0x85, 0xa4, 0x70]);
+
+        // Spans more than one 136-byte block
+        let long = [0x61u8; 200];
+        assert_ne!(keccak256(&long), keccak256(&long[..199]));
+    }
+
+    #[test]
+    fn test_root_set_commitment() {
+        let roots = [[1u8; 32], [2u8; 32]];
+        assert_eq!(
+            root_set_commitment(&roots),
+            keccak256(&[[1u8; 32], [2u8; 32]].concat())
+        );
+        assert_ne!(
+            root_set_commitment(&roots),
+            root_set_commitment(&[[2u8; 32], [1u8; 32]])
+        );
+        assert_ne!(root_set_commitment(&roots[..1]), roots[0]);
+    }
+
+    #[test]
+    fn test_claim_output_abi_layout() {
+        // Mirrors abi.encodePacked(MERKLE_ROOT, nullifier, uint64(1), USER, CAMPAIGN_ID, true,
+        // HASH_SCHEME, AMOUNT, basketDigest, TIMESTAMP, claimedAmount, nextNullifier, minBalance,
+        // exclusionRoot, policyCommitment, relayer, fee, chainId, verifyingContract, notBefore,
+        // expiresAt, TOKEN, tokenAmount) in ZKAirdrop.t.sol
+        let mut merkle_root = [0u8; 32];
+        merkle_root[29..].copy_from_slice(&[0xab, 0xcd, 0xef]);
+        let mut recipient = [0u8; 20];
+        recipient[19] = 1;
+        let basket_entry = TokenAllocation {
+            token: [6u8; 20],
+            amount: 42,
+        };
+        let output = ClaimOutput {
+            merkle_root,
+            nullifier: [7u8; 32],
+            epoch_id: 1,
+            recipient,
+            campaign_id: [5u8; 32],
+            ownership_verified: true,
+            hash_scheme: HashScheme::Keccak256,
+            amount: 1_000_000_000_000_000_000,
+            basket_digest: allocation::basket_digest(&[basket_entry]).unwrap(),
+            timestamp: 1_700_000_000,
+            claimed_amount: 3,
+            next_nullifier: [8u8; 32],
+            min_balance: 0,
+            exclusion_root: [9u8; 32],
+            policy_commitment: [10u8; 32],
+            relayer: [11u8; 20],
+            fee: 12,
+            chain_id: 13,
+            verifying_contract: [14u8; 20],
+            not_before: 15,
+            expires_at: 16,
+            basket: vec![basket_entry],
+        };
+
+        let bytes = output.to_abi_bytes();
+        assert_eq!(bytes.len(), ClaimOutput::HEAD_LEN + 52);
+        assert_eq!(&bytes[0..32], &merkle_root);
+        assert_eq!(&bytes[32..64], &[7u8; 32]);
+        assert_eq!(&bytes[64..72], &[0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(&bytes[72..92], &recipient);
+        assert_eq!(&bytes[92..124], &[5u8; 32]);
+        assert_eq!(bytes[124], 1);
+        assert_eq!(bytes[125], 1);
+        assert_eq!(&bytes[126..158], &amount_word(1_000_000_000_000_000_000));
+        assert_eq!(
+            &bytes[158..190],
+            &allocation::basket_digest(&[basket_entry]).unwrap()
+        );
+        assert_eq!(&bytes[190..198], &1_700_000_000u64.to_be_bytes());
+        assert_eq!(&bytes[198..230], &amount_word(3));
+        assert_eq!(&bytes[230..262], &[8u8; 32]);
+        assert_eq!(&bytes[262..294], &[0u8; 32]);
+        assert_eq!(&bytes[294..326], &[9u8; 32]);
+        assert_eq!(&bytes[326..358], &[10u8; 32]);
+        assert_eq!(&bytes[358..378], &[11u8; 20]);
+        assert_eq!(&bytes[378..410], &amount_word(12));
+        assert_eq!(&bytes[410..418], &13u64.to_be_bytes());
+        assert_eq!(&bytes[418..438], &[14u8; 20]);
+        assert_eq!(&bytes[438..446], &15u64.to_be_bytes());
+        assert_eq!(&bytes[446..454], &16u64.to_be_bytes());
+        assert_eq!(&bytes[454..474], &[6u8; 20]);
+        assert_eq!(&bytes[474..506], &amount_word(42));
+    }
+
+    #[test]
+    fn test_claim_output_abi_roundtrip() {
+        let output = ClaimOutput {
+            merkle_root: [1u8; 32],
+            nullifier: [2u8; 32],
+            epoch_id: 0x0102_0304_0506_0708,
+            recipient: [3u8; 20],
+            campaign_id: [4u8; 32],
+            ownership_verified: false,
+            hash_scheme: HashScheme::Sha256,
+            amount: u128::MAX,
+            basket_digest: allocation::EMPTY_BASKET_DIGEST,
+            timestamp: 0,
+            claimed_amount: 0,
+            next_nullifier: [0u8; 32],
+            min_balance: 1_000,
+            exclusion_root: [0u8; 32],
+            policy_commitment: [0u8; 32],
+            relayer: [0u8; 20],
+            fee: 0,
+            chain_id: 1,
+            verifying_contract: [5u8; 20],
+            not_before: 0,
+            expires_at: 1_700_003_600,
+            basket: Vec::new(),
+        };
+
+        let mut bytes = output.to_abi_bytes();
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes), Some(output.clone()));
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes[..72]), None);
+
+        // Booleans are a single 0/1 byte
+        bytes[124] = 2;
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes), None);
+
+        // Unknown hash schemes are rejected
+        bytes[124] = 0;
+        bytes[125] = 9;
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes), None);
+
+        // Amounts above u128 are rejected
+        bytes[125] = 0;
+        bytes[126] = 1;
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes), None);
+
+        // Claimed amounts above u128 are rejected too
+        bytes[126] = 0;
+        bytes[198] = 1;
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes), None);
+
+        // Basket entries must be canonical and match the digest
+        let a = TokenAllocation {
+            token: [1u8; 20],
+            amount: 5,
+        };
+        let b = TokenAllocation {
+            token: [2u8; 20],
+            amount: 6,
+        };
+        let output = ClaimOutput {
+            basket_digest: allocation::basket_digest(&[a, b]).unwrap(),
+            basket: vec![a, b],
+            ..output
+        };
+        let bytes = output.to_abi_bytes();
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes), Some(output.clone()));
+        assert_eq!(ClaimOutput::from_abi_bytes(&bytes[..bytes.len() - 1]), None);
+
+        let swapped = ClaimOutput {
+            basket: vec![b, a],
+            ..output.clone()
+        };
+        assert_eq!(ClaimOutput::from_abi_bytes(&swapped.to_abi_bytes()), None);
+
+        let tampered = ClaimOutput {
+            basket_digest: [0u8; 32],
+            ..output
+        };
+        assert_eq!(ClaimOutput::from_abi_bytes(&tampered.to_abi_bytes()), None);
+    }
 }
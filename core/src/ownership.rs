@@ -0,0 +1,206 @@
+use crate::{keccak256, PublicInputs};
+
+/// Public fields of a claim the eligible address signs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClaimTerms {
+    /// Campaign the claim is made for
+    pub campaign_id: [u8; 32],
+    /// Epoch the claim is made in
+    pub epoch_id: u64,
+    /// Chain of the deployment the claim is made on
+    pub chain_id: u64,
+    /// `ZKAirdrop` deployment the claim is made on
+    pub verifying_contract: [u8; 20],
+    /// Address that receives the payout
+    pub recipient: [u8; 20],
+    /// Relayer paid `fee`; zero for any submitter
+    pub relayer: [u8; 20],
+    /// Cut of the payout paid to `relayer`
+    pub fee: u128,
+}
+
+impl ClaimTerms {
+    /// Terms of a claim proven with `public_inputs` and paid to `recipient`,
+    /// which batch claims choose per claim
+    pub fn new(public_inputs: &PublicInputs, recipient: &[u8; 20]) -> Self {
+        ClaimTerms {
+            campaign_id: public_inputs.campaign_id,
+            epoch_id: public_inputs.epoch_id,
+            chain_id: public_inputs.chain_id,
+            verifying_contract: public_inputs.verifying_contract,
+            recipient: *recipient,
+            relayer: public_inputs.relayer,
+            fee: public_inputs.fee,
+        }
+    }
+}
+
+/// Build the human-readable claim message the eligible address signs
+///
+/// Binding every field of `terms` means a signature cannot be replayed for
+/// another campaign, another round, another deployment or another payout
+/// address, nor re-proven with a relayer or fee the claimer did not agree to.
+pub fn claim_message(terms: &ClaimTerms) -> Vec<u8> {
+    format!(
+        concat!(
+            "ZKAirdrop claim\ncampaign: 0x{}\nepoch: {}\nchain: {}\ncontract: 0x{}\n",
+            "recipient: 0x{}\nrelayer: 0x{}\nfee: {}"
+        ),
+        to_hex(&terms.campaign_id),
+        terms.epoch_id,
+        terms.chain_id,
+        to_hex(&terms.verifying_contract),
+        to_hex(&terms.recipient),
+        to_hex(&terms.relayer),
+        terms.fee
+    )
+    .into_bytes()
+}
+
+/// Hash a message the way `personal_sign` does (EIP-191 version 0x45)
+pub fn personal_message_hash(message: &[u8]) -> [u8; 32] {
+    let mut prefixed = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+    prefixed.extend_from_slice(message);
+    keccak256(&prefixed)
+}
+
+/// Recover the Ethereum address that `personal_sign`ed `message`
+///
+/// `signature` is the 65-byte `r || s || v` form, with `v` either 0/1 or 27/28.
+/// Returns `None` for malformed or unrecoverable signatures.
+pub fn recover_signer(message: &[u8], signature: &[u8]) -> Option<[u8; 20]> {
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    if signature.len() != 65 {
+        return None;
+    }
+
+    let sig = Signature::from_slice(&signature[..64]).ok()?;
+    let v = match signature[64] {
+        27 | 28 => signature[64] - 27,
+        v => v,
+    };
+    let recovery_id = RecoveryId::from_byte(v)?;
+
+    let hash = personal_message_hash(message);
+    let key = VerifyingKey::recover_from_prehash(&hash, &sig, recovery_id).ok()?;
+    Some(public_key_address(&key))
+}
+
+/// Sign `message` with `personal_sign` semantics, returning `r || s || v` with `v` in 27/28
+pub fn sign_personal_message(private_key: &[u8; 32], message: &[u8]) -> Option<Vec<u8>> {
+    use k256::ecdsa::SigningKey;
+
+    let key = SigningKey::from_slice(private_key).ok()?;
+    let hash = personal_message_hash(message);
+    let (sig, recovery_id) = key.sign_prehash_recoverable(&hash).ok()?;
+
+    let mut signature = sig.to_bytes().to_vec();
+    signature.push(recovery_id.to_byte() + 27);
+    Some(signature)
+}
+
+/// Derive the Ethereum address controlled by a private key
+pub fn private_key_address(private_key: &[u8; 32]) -> Option<[u8; 20]> {
+    use k256::ecdsa::SigningKey;
+
+    let key = SigningKey::from_slice(private_key).ok()?;
+    Some(public_key_address(key.verifying_key()))
+}
+
+fn public_key_address(key: &k256::ecdsa::VerifyingKey) -> [u8; 20] {
+    use k256::elliptic_curve::sec1::ToEncodedPoint;
+
+    let point = k256::PublicKey::from(key).to_encoded_point(false);
+    // Skip the 0x04 uncompressed-point prefix
+    let hash = keccak256(&point.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_personal_message_hash() {
+        // keccak256("\x19Ethereum Signed Message:\n11hello world")
+        assert_eq!(
+            to_hex(&personal_message_hash(b"hello world")),
+            "d9eba16ed0ecae432b71fe008c98cc872bb4cc214d3220a36f365326cf807d68"
+        );
+    }
+
+    const TERMS: ClaimTerms = ClaimTerms {
+        campaign_id: [1u8; 32],
+        epoch_id: 7,
+        chain_id: 1,
+        verifying_contract: [0xc0u8; 20],
+        recipient: [0xaau8; 20],
+        relayer: [0u8; 20],
+        fee: 0,
+    };
+
+    #[test]
+    fn test_claim_message_binds_fields() {
+        let message = claim_message(&TERMS);
+        let text = String::from_utf8(message.clone()).unwrap();
+        assert!(text.contains("epoch: 7"));
+        assert!(text.contains(&format!("recipient: 0x{}", "aa".repeat(20))));
+
+        let changed = [
+            ClaimTerms {
+                epoch_id: 8,
+                ..TERMS
+            },
+            ClaimTerms {
+                campaign_id: [2u8; 32],
+                ..TERMS
+            },
+            ClaimTerms {
+                chain_id: 10,
+                ..TERMS
+            },
+            ClaimTerms {
+                verifying_contract: [0xc1u8; 20],
+                ..TERMS
+            },
+            ClaimTerms {
+                recipient: [0xbbu8; 20],
+                ..TERMS
+            },
+            ClaimTerms {
+                relayer: [0xccu8; 20],
+                ..TERMS
+            },
+            ClaimTerms { fee: 1, ..TERMS },
+        ];
+        for terms in &changed {
+            assert_ne!(message, claim_message(terms));
+        }
+    }
+
+    #[test]
+    fn test_sign_and_recover() {
+        let private_key = [0x11u8; 32];
+        let address = private_key_address(&private_key).unwrap();
+        let message = claim_message(&TERMS);
+
+        let signature = sign_personal_message(&private_key, &message).unwrap();
+        assert_eq!(signature.len(), 65);
+        assert_eq!(recover_signer(&message, &signature), Some(address));
+
+        // A signature over a different recipient recovers someone else
+        let other = claim_message(&ClaimTerms {
+            recipient: [0xbbu8; 20],
+            ..TERMS
+        });
+        assert_ne!(recover_signer(&other, &signature), Some(address));
+        assert_eq!(recover_signer(&message, &signature[..64]), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Eligibility policies over several lists
+//!
+//! A policy names the roots of additional `(address, amount)` lists and how
+//! many of them the claimer must be on: all of them for an intersection
+//! ("voters who also hold the NFT"), one for a union, or any threshold in
+//! between. Only the policy commitment reaches the journal.
+
+use serde::{Deserialize, Serialize};
+
+use crate::standard::{abi_encode_address, standard_leaf, verify_sorted_merkle_proof};
+use crate::{amount_word, compute_leaf, keccak256, verify_merkle_proof, MerkleHasher};
+
+/// `threshold`-of-`roots.len()` membership requirement
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListPolicy {
+    /// Roots of the lists, hashed like the eligibility tree
+    pub roots: Vec<[u8; 32]>,
+    /// Number of distinct lists the claimer must be on
+    pub threshold: u32,
+}
+
+impl ListPolicy {
+    /// Require membership in every list (AND)
+    pub fn all(roots: Vec<[u8; 32]>) -> Self {
+        let threshold = roots.len() as u32;
+        ListPolicy { roots, threshold }
+    }
+
+    /// Require membership in at least one list (OR)
+    pub fn any(roots: Vec<[u8; 32]>) -> Self {
+        ListPolicy {
+            roots,
+            threshold: 1,
+        }
+    }
+
+    /// Whether the threshold is between one and the number of lists
+    pub fn is_valid(&self) -> bool {
+        self.threshold >= 1 && self.threshold as usize <= self.roots.len()
+    }
+
+    /// `keccak256(abi.encodePacked(uint256(threshold), bytes32[] roots))`
+    pub fn commitment(&self) -> [u8; 32] {
+        let mut packed = amount_word(self.threshold as u128).to_vec();
+        packed.extend_from_slice(self.roots.as_flattened());
+        keccak256(&packed)
+    }
+
+    /// Whether `proofs` satisfy the policy, checking each one with `verify`
+    /// against the root it names
+    ///
+    /// Proofs must name strictly ascending lists so no list counts twice, and
+    /// every proof must verify.
+    pub fn is_satisfied_by(
+        &self,
+        proofs: &[ListProof],
+        verify: impl Fn(&ListProof, &[u8; 32]) -> bool,
+    ) -> bool {
+        if !self.is_valid()
+            || proofs.len() < self.threshold as usize
+            || !proofs.windows(2).all(|pair| pair[0].list < pair[1].list)
+        {
+            return false;
+        }
+
+        proofs.iter().all(|proof| {
+            self.roots
+                .get(proof.list as usize)
+                .is_some_and(|root| verify(proof, root))
+        })
+    }
+}
+
+/// Membership of the claimer's address in one list of a `ListPolicy`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ListProof {
+    /// Index of the list in `ListPolicy::roots`
+    pub list: u32,
+    /// Amount stored with the address in that list
+    pub amount: u128,
+    /// Merkle proof path
+    pub merkle_proof: Vec<[u8; 32]>,
+    /// Position of the leaf in the list's tree
+    pub leaf_index: u32,
+    /// Number of leaves in the list's tree
+    pub leaf_count: u32,
+}
+
+impl ListProof {
+    /// Verify `address` against a list built by `host::MerkleTree::new`
+    pub fn verify<H: MerkleHasher>(&self, address: &[u8; 20], root: &[u8; 32]) -> bool {
+        verify_merkle_proof::<H>(
+            &compute_leaf::<H>(address, self.amount),
+            &self.merkle_proof,
+            self.leaf_index,
+            self.leaf_count,
+            root,
+        )
+    }
+
+    /// Verify `address` against an OpenZeppelin `["address", "uint256"]` tree
+    pub fn verify_standard(&self, address: &[u8; 20], root: &[u8; 32]) -> bool {
+        let leaf = standard_leaf(&[abi_encode_address(address), amount_word(self.amount)]);
+        verify_sorted_merkle_proof(&leaf, &self.merkle_proof, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commit_root, hash_pair, Sha256Hasher as H};
+
+    /// Root and proof of `address` in a two-leaf list
+    fn list(address: &[u8; 20], list: u32) -> ([u8; 32], ListProof) {
+        let sibling = compute_leaf::<H>(&[0xee; 20], 5);
+        let leaf = compute_leaf::<H>(address, 1);
+        let root = commit_root::<H>(&hash_pair::<H>(&leaf, &sibling), 2);
+        let proof = ListProof {
+            list,
+            amount: 1,
+            merkle_proof: vec![sibling],
+            leaf_index: 0,
+            leaf_count: 2,
+        };
+        (root, proof)
+    }
+
+    #[test]
+    fn test_all_and_any() {
+        let address = [1u8; 20];
+        let (voters, voter_proof) = list(&address, 0);
+        let (holders, holder_proof) = list(&address, 1);
+        let verify = |proof: &ListProof, root: &[u8; 32]| proof.verify::<H>(&address, root);
+
+        let both = [voter_proof.clone(), holder_proof.clone()];
+        let all = ListPolicy::all(vec![voters, holders]);
+        assert!(all.is_satisfied_by(&both, verify));
+        assert!(!all.is_satisfied_by(&both[..1], verify));
+
+        // A list cannot be counted twice
+        let twice = [voter_proof.clone(), voter_proof.clone()];
+        assert!(!all.is_satisfied_by(&twice, verify));
+
+        let any = ListPolicy::any(vec![voters, holders]);
+        assert!(any.is_satisfied_by(&both[1..], verify));
+        assert!(!any.is_satisfied_by(&[], verify));
+
+        // Every proof given must verify
+        let wrong = ListProof {
+            amount: 2,
+            ..holder_proof
+        };
+        assert!(!any.is_satisfied_by(&[voter_proof, wrong], verify));
+    }
+
+    #[test]
+    fn test_commitment_binds_threshold() {
+        let roots = vec![[1u8; 32], [2u8; 32]];
+        assert_ne!(
+            ListPolicy::all(roots.clone()).commitment(),
+            ListPolicy::any(roots.clone()).commitment()
+        );
+        assert!(!ListPolicy::all(Vec::new()).is_valid());
+        assert!(!ListPolicy {
+            roots,
+            threshold: 3
+        }
+        .is_valid());
+    }
+}
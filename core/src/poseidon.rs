@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+use ark_bn254::Fr;
+use ark_ff::{AdditiveGroup, BigInteger, Field, PrimeField};
+
+use crate::hasher::{HashScheme, MerkleHasher};
+
+/// Poseidon width (capacity 1 + rate 2), as used for circomlib `Poseidon(2)`
+const WIDTH: usize = 3;
+
+/// Full rounds
+const FULL_ROUNDS: usize = 8;
+
+/// Partial rounds for width 3 over BN254
+const PARTIAL_ROUNDS: usize = 57;
+
+/// Capacity element for internal nodes; zero matches circomlib `Poseidon(2)`
+const NODE_DOMAIN: u64 = 0;
+
+/// Capacity element for roots (leaf count commitment)
+const ROOT_DOMAIN: u64 = 2;
+
+/// Leaf capacity elements are `LEAF_DOMAIN + byte length`, which also
+/// disambiguates the zero padding of the last chunk
+const LEAF_DOMAIN: u64 = 1 << 32;
+
+/// Bytes per absorbed field element (always below the BN254 modulus)
+const CHUNK_BYTES: usize = 31;
+
+/// Poseidon over the BN254 scalar field (x^5, t = 3, R_F = 8, R_P = 57)
+///
+/// Round constants and the MDS matrix are derived with the Grain LFSR from the
+/// reference parameter script, which reproduces the circomlib parameters.
+/// Internal nodes are `Poseidon(left, right)` exactly as circomlib computes it,
+/// so paths can be checked by circom/semaphore-style circuits. Leaves and roots
+/// are domain separated through the capacity element instead of a tag byte.
+pub struct PoseidonHasher;
+
+impl MerkleHasher for PoseidonHasher {
+    const SCHEME: HashScheme = HashScheme::Poseidon;
+
+    fn digest(parts: &[&[u8]]) -> [u8; 32] {
+        Self::hash_leaf(&parts.concat())
+    }
+
+    fn hash_leaf(data: &[u8]) -> [u8; 32] {
+        let mut state = [
+            Fr::from(LEAF_DOMAIN + data.len() as u64),
+            Fr::ZERO,
+            Fr::ZERO,
+        ];
+        for pair in data.chunks(2 * CHUNK_BYTES) {
+            for (slot, chunk) in state[1..].iter_mut().zip(pair.chunks(CHUNK_BYTES)) {
+                *slot += Fr::from_be_bytes_mod_order(chunk);
+            }
+            permute(&mut state);
+        }
+        if data.is_empty() {
+            permute(&mut state);
+        }
+        to_bytes(&state[0])
+    }
+
+    fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut state = [
+            Fr::from(NODE_DOMAIN),
+            Fr::from_be_bytes_mod_order(left),
+            Fr::from_be_bytes_mod_order(right),
+        ];
+        permute(&mut state);
+        to_bytes(&state[0])
+    }
+
+    fn hash_root(top: &[u8; 32], leaf_count: u32) -> [u8; 32] {
+        let mut state = [
+            Fr::from(ROOT_DOMAIN),
+            Fr::from(leaf_count as u64),
+            Fr::from_be_bytes_mod_order(top),
+        ];
+        permute(&mut state);
+        to_bytes(&state[0])
+    }
+}
+
+struct Params {
+    round_constants: Vec<Fr>,
+    mds: [[Fr; WIDTH]; WIDTH],
+}
+
+fn params() -> &'static Params {
+    static PARAMS: OnceLock<Params> = OnceLock::new();
+    PARAMS.get_or_init(|| {
+        let mut grain = Grain::new();
+        let round_constants = (0..(FULL_ROUNDS + PARTIAL_ROUNDS) * WIDTH)
+            .map(|_| grain.next_field_element())
+            .collect();
+
+        // Cauchy matrix 1 / (x_i + y_j) over 2t sampled elements
+        let samples: Vec<Fr> = (0..2 * WIDTH)
+            .map(|_| Fr::from_be_bytes_mod_order(&grain.next_bytes()))
+            .collect();
+        let mut mds = [[Fr::ZERO; WIDTH]; WIDTH];
+        for (i, row) in mds.iter_mut().enumerate() {
+            for (j, entry) in row.iter_mut().enumerate() {
+                *entry = (samples[i] + samples[WIDTH + j])
+                    .inverse()
+                    .expect("Cauchy matrix entry is invertible");
+            }
+        }
+
+        Params {
+            round_constants,
+            mds,
+        }
+    })
+}
+
+fn permute(state: &mut [Fr; WIDTH]) {
+    let params = params();
+    let half_full = FULL_ROUNDS / 2;
+
+    for round in 0..FULL_ROUNDS + PARTIAL_ROUNDS {
+        for (i, value) in state.iter_mut().enumerate() {
+            *value += params.round_constants[round * WIDTH + i];
+        }
+
+        if round < half_full || round >= half_full + PARTIAL_ROUNDS {
+            state.iter_mut().for_each(sbox);
+        } else {
+            sbox(&mut state[0]);
+        }
+
+        let mut mixed = [Fr::ZERO; WIDTH];
+        for (i, out) in mixed.iter_mut().enumerate() {
+            for (j, value) in state.iter().enumerate() {
+                *out += params.mds[i][j] * value;
+            }
+        }
+        *state = mixed;
+    }
+}
+
+fn sbox(value: &mut Fr) {
+    let square = value.square();
+    *value *= square.square();
+}
+
+fn to_bytes(value: &Fr) -> [u8; 32] {
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&value.into_bigint().to_bytes_be());
+    output
+}
+
+/// Grain LFSR in self-shrinking mode, seeded with the Poseidon parameters
+struct Grain {
+    bits: VecDeque<bool>,
+}
+
+impl Grain {
+    const FIELD_BITS: usize = 254;
+
+    fn new() -> Self {
+        let mut bits = VecDeque::with_capacity(80);
+        let mut push = |value: u64, width: usize| {
+            for i in (0..width).rev() {
+                bits.push_back((value >> i) & 1 == 1);
+            }
+        };
+        push(1, 2); // prime field
+        push(0, 4); // x^alpha S-box
+        push(Self::FIELD_BITS as u64, 12);
+        push(WIDTH as u64, 12);
+        push(FULL_ROUNDS as u64, 10);
+        push(PARTIAL_ROUNDS as u64, 10);
+        push((1 << 30) - 1, 30);
+
+        let mut grain = Grain { bits };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let b = &self.bits;
+        let bit = b[62] ^ b[51] ^ b[38] ^ b[23] ^ b[13] ^ b[0];
+        self.bits.pop_front();
+        self.bits.push_back(bit);
+        bit
+    }
+
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.step();
+            let bit = self.step();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Next `FIELD_BITS` output bits as a big-endian 32-byte integer
+    fn next_bytes(&mut self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        let offset = 256 - Self::FIELD_BITS;
+        for i in 0..Self::FIELD_BITS {
+            if self.next_bit() {
+                let position = offset + i;
+                bytes[position / 8] |= 0x80 >> (position % 8);
+            }
+        }
+        bytes
+    }
+
+    /// Rejection-sample a canonical field element
+    fn next_field_element(&mut self) -> Fr {
+        loop {
+            let bytes = self.next_bytes();
+            let value = Fr::from_be_bytes_mod_order(&bytes);
+            if to_bytes(&value) == bytes {
+                return value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_bytes(value: u64) -> [u8; 32] {
+        to_bytes(&Fr::from(value))
+    }
+
+    #[test]
+    fn test_matches_circomlib_poseidon2() {
+        // circomlibjs: poseidon([1, 2])
+        let hash = PoseidonHasher::hash_node(&field_bytes(1), &field_bytes(2));
+        let expected = "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a";
+        let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, expected);
+    }
+
+    #[test]
+    fn test_domains_are_separated() {
+        let a = field_bytes(1);
+        let b = field_bytes(2);
+        assert_ne!(
+            PoseidonHasher::hash_node(&a, &b),
+            PoseidonHasher::hash_root(&b, 1)
+        );
+        assert_ne!(
+            PoseidonHasher::hash_leaf(&[1u8; 20]),
+            PoseidonHasher::hash_leaf(&[1u8; 21])
+        );
+        assert_ne!(
+            PoseidonHasher::hash_leaf(&[]),
+            PoseidonHasher::hash_leaf(&[0u8])
+        );
+    }
+}
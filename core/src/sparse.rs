@@ -0,0 +1,159 @@
+//! Sparse Merkle tree over a 256-bit key space
+//!
+//! Every key has a fixed slot; an occupied slot holds `sparse_leaf(key)` and an
+//! empty one holds `EMPTY_LEAF`. Proving the slot of a key against a root shows
+//! either membership or non-membership, which is what exclusion lists need.
+//! Proofs only carry the siblings that differ from an empty subtree.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{keccak256, MerkleHasher, EMPTY_LEAF};
+
+/// Number of levels below the root
+pub const SPARSE_DEPTH: usize = 256;
+
+/// Prefix of occupied sparse leaves
+const SPARSE_LEAF_TAG: &[u8] = b"zkairdrop.sparse.v1";
+
+/// Path from a key's slot to the root
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SparseMerkleProof {
+    /// Bit `level` is set when the sibling at `level` (0 = leaf level) is not an empty subtree
+    pub bitmap: [u8; 32],
+    /// Non-empty siblings, leaf level first
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl SparseMerkleProof {
+    /// Whether the sibling at `level` is carried in `siblings`
+    pub fn has_sibling(&self, level: usize) -> bool {
+        self.bitmap[level / 8] & (1 << (level % 8)) != 0
+    }
+
+    /// Mark the sibling at `level` as carried in `siblings`
+    pub fn set_sibling(&mut self, level: usize) {
+        self.bitmap[level / 8] |= 1 << (level % 8);
+    }
+}
+
+/// Key of an address in a sparse tree
+pub fn sparse_key(address: &[u8; 20]) -> [u8; 32] {
+    keccak256(address)
+}
+
+/// Leaf stored in the slot of an occupied key
+pub fn sparse_leaf<H: MerkleHasher>(key: &[u8; 32]) -> [u8; 32] {
+    H::hash_leaf(&[SPARSE_LEAF_TAG, key.as_slice()].concat())
+}
+
+/// Whether `key` branches right at `level` (0 = leaf level); the most
+/// significant bit picks the branch below the root
+pub fn key_bit(key: &[u8; 32], level: usize) -> bool {
+    let bit = SPARSE_DEPTH - 1 - level;
+    key[bit / 8] & (0x80 >> (bit % 8)) != 0
+}
+
+/// Root of an empty subtree at each height, from `EMPTY_LEAF` up to the empty root
+pub fn empty_subtrees<H: MerkleHasher>() -> Vec<[u8; 32]> {
+    let mut hashes = Vec::with_capacity(SPARSE_DEPTH + 1);
+    hashes.push(EMPTY_LEAF);
+    for level in 0..SPARSE_DEPTH {
+        hashes.push(H::hash_node(&hashes[level], &hashes[level]));
+    }
+    hashes
+}
+
+/// Fold `leaf` up the path of `key`, or `None` if the proof is malformed
+pub fn compute_sparse_root<H: MerkleHasher>(
+    key: &[u8; 32],
+    leaf: &[u8; 32],
+    proof: &SparseMerkleProof,
+) -> Option<[u8; 32]> {
+    let empty = empty_subtrees::<H>();
+    let mut siblings = proof.siblings.iter();
+    let mut computed_hash = *leaf;
+
+    for (level, empty_sibling) in empty.iter().take(SPARSE_DEPTH).enumerate() {
+        let sibling = if proof.has_sibling(level) {
+            siblings.next()?
+        } else {
+            empty_sibling
+        };
+        computed_hash = if key_bit(key, level) {
+            H::hash_node(sibling, &computed_hash)
+        } else {
+            H::hash_node(&computed_hash, sibling)
+        };
+    }
+
+    // Every carried sibling must be used
+    if siblings.next().is_some() {
+        return None;
+    }
+    Some(computed_hash)
+}
+
+/// Verify that `key` is in the tree with root `root`
+pub fn verify_sparse_membership<H: MerkleHasher>(
+    key: &[u8; 32],
+    proof: &SparseMerkleProof,
+    root: &[u8; 32],
+) -> bool {
+    compute_sparse_root::<H>(key, &sparse_leaf::<H>(key), proof) == Some(*root)
+}
+
+/// Verify that `key` is not in the tree with root `root`
+pub fn verify_sparse_non_membership<H: MerkleHasher>(
+    key: &[u8; 32],
+    proof: &SparseMerkleProof,
+    root: &[u8; 32],
+) -> bool {
+    compute_sparse_root::<H>(key, &EMPTY_LEAF, proof) == Some(*root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sha256Hasher as H;
+
+    #[test]
+    fn test_single_key_tree() {
+        let key = sparse_key(&[7u8; 20]);
+        let empty_root = empty_subtrees::<H>()[SPARSE_DEPTH];
+
+        // A tree holding only `key`: every sibling on its path is empty
+        let proof = SparseMerkleProof::default();
+        let root = compute_sparse_root::<H>(&key, &sparse_leaf::<H>(&key), &proof).unwrap();
+        assert!(verify_sparse_membership::<H>(&key, &proof, &root));
+        assert!(!verify_sparse_non_membership::<H>(&key, &proof, &root));
+
+        // The empty tree excludes every key
+        assert!(verify_sparse_non_membership::<H>(&key, &proof, &empty_root));
+        assert!(!verify_sparse_membership::<H>(&key, &proof, &empty_root));
+    }
+
+    #[test]
+    fn test_malformed_proof() {
+        let key = sparse_key(&[7u8; 20]);
+        let mut proof = SparseMerkleProof::default();
+
+        // A bitmap bit without its sibling, and a sibling without its bit
+        proof.set_sibling(3);
+        assert!(compute_sparse_root::<H>(&key, &EMPTY_LEAF, &proof).is_none());
+        let proof = SparseMerkleProof {
+            bitmap: [0u8; 32],
+            siblings: vec![[1u8; 32]],
+        };
+        assert!(compute_sparse_root::<H>(&key, &EMPTY_LEAF, &proof).is_none());
+    }
+
+    #[test]
+    fn test_key_bit_order() {
+        let mut key = [0u8; 32];
+        key[0] = 0x80;
+        key[31] = 0x01;
+        assert!(key_bit(&key, SPARSE_DEPTH - 1));
+        assert!(key_bit(&key, 0));
+        assert!(!key_bit(&key, 1));
+    }
+}
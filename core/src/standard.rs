@@ -0,0 +1,76 @@
+//! OpenZeppelin `StandardMerkleTree` compatibility
+//!
+//! Leaves are `keccak256(keccak256(abi.encode(values)))` and internal nodes
+//! hash the sorted pair, so proofs carry no position information and match
+//! `MerkleProof.verify` on-chain.
+
+use crate::{keccak256, BALANCE_LEAF_TAG};
+
+/// ABI-encode an address as a 32-byte word
+pub fn abi_encode_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+/// Leading `bytes32` of balance snapshot leaves, `keccak256("zkairdrop.balance.v1")`,
+/// so a balance leaf never verifies as an `["address", "uint256"]` allocation
+pub fn balance_tag_word() -> [u8; 32] {
+    keccak256(BALANCE_LEAF_TAG)
+}
+
+/// Hash ABI-encoded static values into a standard leaf
+pub fn standard_leaf(words: &[[u8; 32]]) -> [u8; 32] {
+    keccak256(&keccak256(words.concat().as_slice()))
+}
+
+/// Hash two nodes in sorted order
+pub fn hash_sorted_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    if a <= b {
+        keccak256(&[a.as_slice(), b].concat())
+    } else {
+        keccak256(&[b.as_slice(), a].concat())
+    }
+}
+
+/// Verify a sorted-pair Merkle proof (`MerkleProof.verify`)
+pub fn verify_sorted_merkle_proof(leaf: &[u8; 32], proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    let computed_hash = proof.iter().fold(*leaf, |hash, proof_element| {
+        hash_sorted_pair(&hash, proof_element)
+    });
+
+    computed_hash == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount_word;
+
+    fn from_hex(hex: &str) -> [u8; 32] {
+        let mut output = [0u8; 32];
+        for (i, byte) in output.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        output
+    }
+
+    #[test]
+    fn test_matches_openzeppelin_example() {
+        // Example tree from the @openzeppelin/merkle-tree README
+        let a = standard_leaf(&[
+            abi_encode_address(&[0x11u8; 20]),
+            amount_word(5_000_000_000_000_000_000),
+        ]);
+        let b = standard_leaf(&[
+            abi_encode_address(&[0x22u8; 20]),
+            amount_word(2_500_000_000_000_000_000),
+        ]);
+        let root = from_hex("d4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77");
+
+        assert_eq!(hash_sorted_pair(&a, &b), root);
+        assert!(verify_sorted_merkle_proof(&a, &[b], &root));
+        assert!(verify_sorted_merkle_proof(&b, &[a], &root));
+        assert!(!verify_sorted_merkle_proof(&a, &[a], &root));
+    }
+}
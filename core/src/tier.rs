@@ -0,0 +1,220 @@
+//! Tiered rewards over private balance or score leaves
+//!
+//! A tier table lists ascending lower bounds; tier `i` covers scores in
+//! `[bounds[i], bounds[i + 1])` and the last tier is open-ended. The guest
+//! proves a snapshot leaf and commits only the tier index, so the contract
+//! pays per tier while the score itself stays private.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{amount_word, keccak256, HashScheme};
+
+/// Maximum number of tiers; the tier index is committed as a single byte
+pub const MAX_TIERS: usize = 256;
+
+/// Ascending score lower bounds, one per tier
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TierTable {
+    /// Lower bound of each tier, strictly ascending
+    pub lower_bounds: Vec<u128>,
+}
+
+impl TierTable {
+    /// Build a table, or `None` if it is empty, too long or not strictly ascending
+    pub fn new(lower_bounds: Vec<u128>) -> Option<Self> {
+        let table = TierTable { lower_bounds };
+        if !table.is_valid() {
+            return None;
+        }
+        Some(table)
+    }
+
+    /// Whether the table is non-empty, fits `MAX_TIERS` and is strictly ascending
+    pub fn is_valid(&self) -> bool {
+        !self.lower_bounds.is_empty()
+            && self.lower_bounds.len() <= MAX_TIERS
+            && self.lower_bounds.windows(2).all(|pair| pair[0] < pair[1])
+    }
+
+    /// Tier that `score` falls in, or `None` if it is below the first bound
+    pub fn tier_of(&self, score: u128) -> Option<u8> {
+        let tiers = self.lower_bounds.partition_point(|&bound| bound <= score);
+        tiers.checked_sub(1).map(|tier| tier as u8)
+    }
+
+    /// `keccak256(abi.encodePacked(uint256[] lowerBounds))`, the digest the
+    /// contract is configured with
+    pub fn digest(&self) -> [u8; 32] {
+        let packed: Vec<u8> = self
+            .lower_bounds
+            .iter()
+            .flat_map(|&bound| amount_word(bound))
+            .collect();
+        keccak256(&packed)
+    }
+}
+
+/// Output committed to the journal by tiered claims, in place of `ClaimOutput`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TierClaimOutput {
+    /// The verified Merkle root, or the `root_set_commitment` of the accepted roots
+    pub merkle_root: [u8; 32],
+    /// Nullifier to prevent double-claiming
+    pub nullifier: [u8; 32],
+    /// Epoch ID that was verified
+    pub epoch_id: u64,
+    /// Address that receives the reward
+    pub recipient: [u8; 20],
+    /// Campaign the claim was made for
+    pub campaign_id: [u8; 32],
+    /// Whether the claimer proved control of the eligible address
+    pub ownership_verified: bool,
+    /// Hash function the Merkle proof was verified with
+    pub hash_scheme: HashScheme,
+    /// `TierTable::digest` of the table the tier was computed against
+    pub tier_table_digest: [u8; 32],
+    /// Tier the claimer's score falls in
+    pub tier: u8,
+    /// Exclusion tree the claimer proved not to be in; zero without an exclusion list
+    pub exclusion_root: [u8; 32],
+    /// `ListPolicy::commitment` of the policy the claimer satisfied; zero without one
+    pub policy_commitment: [u8; 32],
+    /// Chain the claim is valid on
+    pub chain_id: u64,
+    /// `ZKAirdrop` deployment the claim is valid for
+    pub verifying_contract: [u8; 20],
+    /// Unix time before which the claim cannot be submitted; zero without a lower bound
+    pub not_before: u64,
+    /// Unix time after which the claim can no longer be submitted; zero if it never expires
+    pub expires_at: u64,
+}
+
+impl TierClaimOutput {
+    /// Length of the packed journal
+    pub const ABI_LEN: usize = 267;
+
+    /// Encode as `abi.encodePacked(merkleRoot, nullifier, uint64(epochId), recipient,
+    /// campaignId, ownershipVerified, uint8(hashScheme), tierTableDigest, uint8(tier),
+    /// exclusionRoot, policyCommitment, uint64(chainId), verifyingContract, uint64(notBefore),
+    /// uint64(expiresAt))`
+    pub fn to_abi_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::ABI_LEN);
+        bytes.extend_from_slice(&self.merkle_root);
+        bytes.extend_from_slice(&self.nullifier);
+        bytes.extend_from_slice(&self.epoch_id.to_be_bytes());
+        bytes.extend_from_slice(&self.recipient);
+        bytes.extend_from_slice(&self.campaign_id);
+        bytes.push(self.ownership_verified as u8);
+        bytes.push(self.hash_scheme as u8);
+        bytes.extend_from_slice(&self.tier_table_digest);
+        bytes.push(self.tier);
+        bytes.extend_from_slice(&self.exclusion_root);
+        bytes.extend_from_slice(&self.policy_commitment);
+        bytes.extend_from_slice(&self.chain_id.to_be_bytes());
+        bytes.extend_from_slice(&self.verifying_contract);
+        bytes.extend_from_slice(&self.not_before.to_be_bytes());
+        bytes.extend_from_slice(&self.expires_at.to_be_bytes());
+        bytes
+    }
+
+    /// Decode the packed journal layout, returning `None` on a length mismatch
+    pub fn from_abi_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::ABI_LEN {
+            return None;
+        }
+
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[0..32]);
+        let mut nullifier = [0u8; 32];
+        nullifier.copy_from_slice(&bytes[32..64]);
+        let mut epoch_bytes = [0u8; 8];
+        epoch_bytes.copy_from_slice(&bytes[64..72]);
+        let mut recipient = [0u8; 20];
+        recipient.copy_from_slice(&bytes[72..92]);
+        let mut campaign_id = [0u8; 32];
+        campaign_id.copy_from_slice(&bytes[92..124]);
+        let ownership_verified = match bytes[124] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let hash_scheme = HashScheme::from_u8(bytes[125])?;
+        let mut tier_table_digest = [0u8; 32];
+        tier_table_digest.copy_from_slice(&bytes[126..158]);
+        let mut exclusion_root = [0u8; 32];
+        exclusion_root.copy_from_slice(&bytes[159..191]);
+        let mut policy_commitment = [0u8; 32];
+        policy_commitment.copy_from_slice(&bytes[191..223]);
+        let mut chain_id_bytes = [0u8; 8];
+        chain_id_bytes.copy_from_slice(&bytes[223..231]);
+        let mut verifying_contract = [0u8; 20];
+        verifying_contract.copy_from_slice(&bytes[231..251]);
+        let mut not_before_bytes = [0u8; 8];
+        not_before_bytes.copy_from_slice(&bytes[251..259]);
+        let mut expires_at_bytes = [0u8; 8];
+        expires_at_bytes.copy_from_slice(&bytes[259..267]);
+
+        Some(TierClaimOutput {
+            merkle_root,
+            nullifier,
+            epoch_id: u64::from_be_bytes(epoch_bytes),
+            recipient,
+            campaign_id,
+            ownership_verified,
+            hash_scheme,
+            tier_table_digest,
+            tier: bytes[158],
+            exclusion_root,
+            policy_commitment,
+            chain_id: u64::from_be_bytes(chain_id_bytes),
+            verifying_contract,
+            not_before: u64::from_be_bytes(not_before_bytes),
+            expires_at: u64::from_be_bytes(expires_at_bytes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tier_of() {
+        let table = TierTable::new(vec![10, 100, 1_000]).unwrap();
+        assert_eq!(table.tier_of(9), None);
+        assert_eq!(table.tier_of(10), Some(0));
+        assert_eq!(table.tier_of(99), Some(0));
+        assert_eq!(table.tier_of(100), Some(1));
+        assert_eq!(table.tier_of(u128::MAX), Some(2));
+
+        assert_eq!(TierTable::new(Vec::new()), None);
+        assert_eq!(TierTable::new(vec![10, 10]), None);
+        assert_eq!(TierTable::new((0..=MAX_TIERS as u128).collect()), None);
+    }
+
+    #[test]
+    fn test_tier_output_abi_roundtrip() {
+        let output = TierClaimOutput {
+            merkle_root: [1u8; 32],
+            nullifier: [2u8; 32],
+            epoch_id: 7,
+            recipient: [3u8; 20],
+            campaign_id: [4u8; 32],
+            ownership_verified: true,
+            hash_scheme: HashScheme::Keccak256,
+            tier_table_digest: TierTable::new(vec![1, 2]).unwrap().digest(),
+            tier: 1,
+            exclusion_root: [5u8; 32],
+            policy_commitment: [6u8; 32],
+            chain_id: 1,
+            verifying_contract: [7u8; 20],
+            not_before: 8,
+            expires_at: 9,
+        };
+
+        let bytes = output.to_abi_bytes();
+        assert_eq!(bytes.len(), TierClaimOutput::ABI_LEN);
+        assert_eq!(TierClaimOutput::from_abi_bytes(&bytes), Some(output));
+        assert_eq!(TierClaimOutput::from_abi_bytes(&bytes[1..]), None);
+    }
+}
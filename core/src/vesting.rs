@@ -0,0 +1,135 @@
+//! Linear vesting with a cliff, evaluated inside the guest
+//!
+//! A vesting leaf commits to `(address, total, start, cliff, duration)`. Each
+//! claim pays what vested since the previous claim and is identified by a
+//! tranche nullifier over the amount already claimed, so the next tranche can
+//! only be opened by the claim that precedes it. Tranche nullifiers are built
+//! on the nullifier of `VESTING_EPOCH` rather than the claim's epoch, so the
+//! chain carries over when a new epoch republishes the leaf.
+
+use serde::{Deserialize, Serialize};
+
+/// Domain tag for tranche nullifiers
+const TRANCHE_NULLIFIER_TAG: &[u8] = b"zkairdrop.tranche.v1";
+
+/// Epoch whose nullifier every tranche chain is built on; contracts start at
+/// epoch 1, so no claim is ever made in it
+pub const VESTING_EPOCH: u64 = 0;
+
+/// Vesting parameters committed in a leaf
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VestingSchedule {
+    /// Unix timestamp vesting starts from
+    pub start: u64,
+    /// Seconds after `start` before anything can be claimed
+    pub cliff: u64,
+    /// Seconds after `start` until the total is vested
+    pub duration: u64,
+}
+
+impl VestingSchedule {
+    /// Packed length in a leaf preimage
+    pub const PACKED_LEN: usize = 24;
+
+    /// Encode as `start || cliff || duration`, each big-endian
+    pub fn to_bytes(&self) -> [u8; Self::PACKED_LEN] {
+        let mut bytes = [0u8; Self::PACKED_LEN];
+        bytes[..8].copy_from_slice(&self.start.to_be_bytes());
+        bytes[8..16].copy_from_slice(&self.cliff.to_be_bytes());
+        bytes[16..].copy_from_slice(&self.duration.to_be_bytes());
+        bytes
+    }
+
+    /// Amount of `total` vested at `timestamp`, rounded down
+    pub fn vested_amount(&self, total: u128, timestamp: u64) -> u128 {
+        let elapsed = timestamp.saturating_sub(self.start);
+        if timestamp < self.start || elapsed < self.cliff {
+            return 0;
+        }
+        if elapsed >= self.duration {
+            return total;
+        }
+
+        // total * elapsed / duration without overflowing u128
+        let duration = self.duration as u128;
+        let elapsed = elapsed as u128;
+        (total / duration) * elapsed + (total % duration) * elapsed / duration
+    }
+
+    /// Payout of the tranche after `claimed_amount`: what vested at `timestamp`
+    /// beyond it, or `None` if nothing did
+    pub fn tranche_amount(
+        &self,
+        total: u128,
+        timestamp: u64,
+        claimed_amount: u128,
+    ) -> Option<u128> {
+        self.vested_amount(total, timestamp)
+            .checked_sub(claimed_amount)
+            .filter(|&amount| amount > 0)
+    }
+}
+
+/// Nullifier of the tranche that starts after `claimed_amount` was paid out,
+/// given the `VESTING_EPOCH` nullifier
+pub fn compute_tranche_nullifier(nullifier: &[u8; 32], claimed_amount: u128) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(TRANCHE_NULLIFIER_TAG);
+    hasher.update(nullifier);
+    hasher.update(claimed_amount.to_be_bytes());
+    let result = hasher.finalize();
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&result);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vested_amount() {
+        let schedule = VestingSchedule {
+            start: 1_000,
+            cliff: 100,
+            duration: 400,
+        };
+
+        assert_eq!(schedule.vested_amount(1_000, 0), 0);
+        assert_eq!(schedule.vested_amount(1_000, 1_099), 0);
+        assert_eq!(schedule.vested_amount(1_000, 1_100), 250);
+        assert_eq!(schedule.vested_amount(1_000, 1_333), 832);
+        assert_eq!(schedule.vested_amount(1_000, 1_400), 1_000);
+        assert_eq!(schedule.vested_amount(1_000, u64::MAX), 1_000);
+
+        // No overflow for large totals
+        assert_eq!(schedule.vested_amount(u128::MAX, 1_200), u128::MAX / 2);
+
+        // A tranche pays what vested since the previous one
+        assert_eq!(schedule.tranche_amount(1_000, 1_333, 250), Some(582));
+        assert_eq!(schedule.tranche_amount(1_000, 1_333, 832), None);
+        assert_eq!(schedule.tranche_amount(1_000, 1_099, 0), None);
+    }
+
+    #[test]
+    fn test_zero_duration_vests_at_cliff() {
+        let schedule = VestingSchedule {
+            start: 10,
+            cliff: 0,
+            duration: 0,
+        };
+        assert_eq!(schedule.vested_amount(5, 9), 0);
+        assert_eq!(schedule.vested_amount(5, 10), 5);
+    }
+
+    #[test]
+    fn test_tranche_nullifiers_differ() {
+        let nullifier = [3u8; 32];
+        assert_ne!(
+            compute_tranche_nullifier(&nullifier, 0),
+            compute_tranche_nullifier(&nullifier, 1)
+        );
+        assert_ne!(compute_tranche_nullifier(&nullifier, 0), nullifier);
+    }
+}
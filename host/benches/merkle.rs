@@ -0,0 +1,90 @@
+//! Build and proof timings for `MerkleTree` at snapshot scale
+//!
+//! Run with `cargo bench -p host --bench merkle`, adding `--features parallel`
+//! for the rayon build. Each size also times one proof extracted by rehashing
+//! the padded tree, which is what every `get_proof` call cost before the
+//! levels were kept, and the same tree built into a memory-mapped file.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use core::{compute_leaf, hash_pair, Sha256Hasher, EMPTY_LEAF};
+use host::{DiskTreeBuilder, MerkleTree};
+
+const SIZES: [usize; 2] = [1_000_000, 10_000_000];
+const PROOFS: usize = 100_000;
+
+fn main() {
+    let dir = tempfile::tempdir().expect("Cannot create a temporary directory");
+
+    for size in SIZES {
+        let leaves: Vec<[u8; 32]> = (0..size as u64)
+            .map(|i| {
+                let mut address = [0u8; 20];
+                address[12..].copy_from_slice(&i.to_be_bytes());
+                compute_leaf::<Sha256Hasher>(&address, 1_000)
+            })
+            .collect();
+
+        let start = Instant::now();
+        let tree = MerkleTree::<Sha256Hasher>::from_leaves(leaves.clone());
+        let build = start.elapsed();
+
+        // Spread the lookups over the whole tree
+        let start = Instant::now();
+        for i in 0..PROOFS {
+            black_box(tree.get_proof(i * 7_919 % size));
+        }
+        let lookup = start.elapsed() / PROOFS as u32;
+
+        let start = Instant::now();
+        black_box(rehash_proof(&leaves, size / 2));
+        let rehash = start.elapsed();
+
+        let path = dir.path().join("tree.bin");
+        let start = Instant::now();
+        let mut builder = DiskTreeBuilder::<Sha256Hasher>::create(&path).unwrap();
+        for leaf in &leaves {
+            builder.push_leaf(leaf).unwrap();
+        }
+        let disk = builder.finish().unwrap();
+        let disk_build = start.elapsed();
+        assert_eq!(disk.root(), tree.root());
+
+        let start = Instant::now();
+        for i in 0..PROOFS {
+            black_box(disk.get_proof(i * 7_919 % size));
+        }
+        let disk_lookup = start.elapsed() / PROOFS as u32;
+
+        println!("{} leaves", size);
+        println!("  build:             {:?}", build);
+        println!("  get_proof:         {:?} per proof", lookup);
+        println!("  rehash per proof:  {:?}", rehash);
+        println!(
+            "  all proofs:        {:?} with get_proof, ~{:?} rehashing",
+            lookup * size as u32,
+            Duration::from_secs_f64(rehash.as_secs_f64() * size as f64)
+        );
+        println!("  disk build:        {:?}", disk_build);
+        println!("  disk get_proof:    {:?} per proof", disk_lookup);
+    }
+}
+
+/// Proof for `index` by padding and rehashing every level
+fn rehash_proof(leaves: &[[u8; 32]], index: usize) -> Vec<[u8; 32]> {
+    let mut level = leaves.to_vec();
+    level.resize(leaves.len().next_power_of_two(), EMPTY_LEAF);
+
+    let mut proof = Vec::new();
+    let mut index = index;
+    while level.len() > 1 {
+        proof.push(level[index ^ 1]);
+        level = level
+            .chunks(2)
+            .map(|pair| hash_pair::<Sha256Hasher>(&pair[0], &pair[1]))
+            .collect();
+        index /= 2;
+    }
+    proof
+}
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use core::{AggregateClaimOutput, BatchClaimOutput, ClaimOutput};
+use methods::{AGGREGATE_CLAIMS_ELF, AGGREGATE_CLAIMS_ID, GUEST_CODE_FOR_ZK_PROOF_ID};
+use risc0_zkvm::{default_prover, ExecutorEnv, Receipt};
+
+/// Errors from collecting or aggregating claim receipts
+#[derive(Debug)]
+pub enum AggregateError {
+    /// No receipts were collected
+    Empty,
+    /// The receipt does not verify against the claim guest
+    InvalidReceipt(String),
+    /// The journal is not a packed `ClaimOutput` or `AggregateClaimOutput`
+    InvalidJournal,
+    /// The claim does not fold into the collected batch: another campaign,
+    /// a repeated nullifier, or more than a plain allocation
+    Incompatible,
+    /// Proving or verifying the aggregate receipt failed
+    Prover(String),
+}
+
+impl fmt::Display for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AggregateError::Empty => write!(f, "no claim receipts to aggregate"),
+            AggregateError::InvalidReceipt(err) => write!(f, "invalid claim receipt: {}", err),
+            AggregateError::InvalidJournal => write!(f, "journal has an unexpected layout"),
+            AggregateError::Incompatible => write!(f, "claim does not fold into the batch"),
+            AggregateError::Prover(err) => write!(f, "proving failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for AggregateError {}
+
+/// Collects claim receipts proven by claimers and folds them into one receipt
+///
+/// Claimers prove locally and hand over only the receipt, so the aggregator
+/// never sees their inputs. Each receipt becomes an assumption of the
+/// aggregation guest, which verifies it with `env::verify`.
+#[derive(Default)]
+pub struct ClaimAggregator {
+    receipts: Vec<Receipt>,
+    outputs: Vec<ClaimOutput>,
+    nullifiers: HashSet<[u8; 32]>,
+}
+
+impl ClaimAggregator {
+    /// Start with no receipts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify a claim receipt and add it to the batch
+    ///
+    /// Receipts that would make the aggregation guest abort are rejected here,
+    /// so one bad receipt cannot spoil the whole aggregate.
+    pub fn add(&mut self, receipt: Receipt) -> Result<(), AggregateError> {
+        receipt
+            .verify(GUEST_CODE_FOR_ZK_PROOF_ID)
+            .map_err(|err| AggregateError::InvalidReceipt(err.to_string()))?;
+        let output = ClaimOutput::from_abi_bytes(&receipt.journal.bytes)
+            .ok_or(AggregateError::InvalidJournal)?;
+
+        // Only the new claim is checked; the batch is built once in `prove`
+        let first = self.outputs.first().unwrap_or(&output);
+        if !BatchClaimOutput::can_fold(first, &output)
+            || self.nullifiers.contains(&output.nullifier)
+        {
+            return Err(AggregateError::Incompatible);
+        }
+        self.nullifiers.insert(output.nullifier);
+        self.outputs.push(output);
+        self.receipts.push(receipt);
+        Ok(())
+    }
+
+    /// Outputs of the collected claims, in the order they were added
+    pub fn outputs(&self) -> &[ClaimOutput] {
+        &self.outputs
+    }
+
+    /// Number of receipts collected
+    pub fn len(&self) -> usize {
+        self.receipts.len()
+    }
+
+    /// Whether no receipts were collected
+    pub fn is_empty(&self) -> bool {
+        self.receipts.is_empty()
+    }
+
+    /// Prove one receipt committing every collected claim
+    pub fn prove(&self) -> Result<(Receipt, AggregateClaimOutput), AggregateError> {
+        let batch = BatchClaimOutput::from_claims(&self.outputs).ok_or(AggregateError::Empty)?;
+
+        let journals: Vec<&[u8]> = self
+            .receipts
+            .iter()
+            .map(|receipt| receipt.journal.bytes.as_slice())
+            .collect();
+        let mut builder = ExecutorEnv::builder();
+        for receipt in &self.receipts {
+            builder.add_assumption(receipt.clone());
+        }
+        let env = builder
+            .write(&GUEST_CODE_FOR_ZK_PROOF_ID)
+            .and_then(|builder| builder.write(&journals))
+            .and_then(|builder| builder.build())
+            .map_err(|err| AggregateError::Prover(err.to_string()))?;
+
+        let receipt = default_prover()
+            .prove(env, AGGREGATE_CLAIMS_ELF)
+            .map_err(|err| AggregateError::Prover(err.to_string()))?
+            .receipt;
+        receipt
+            .verify(AGGREGATE_CLAIMS_ID)
+            .map_err(|err| AggregateError::Prover(err.to_string()))?;
+
+        let output = AggregateClaimOutput::from_abi_bytes(&receipt.journal.bytes)
+            .filter(|output| output.batch == batch)
+            .ok_or(AggregateError::InvalidJournal)?;
+        Ok((receipt, output))
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use core::{BatchClaim, BatchClaimOutput, PublicInputs};
+use methods::{BATCH_CLAIMS_ELF, BATCH_CLAIMS_ID};
+use risc0_zkvm::{default_prover, ExecutorEnv, Receipt};
+
+/// Errors from proving a batch of claims
+#[derive(Debug)]
+pub enum BatchProveError {
+    /// The batch has no claims
+    Empty,
+    /// The claim at this index has no nullifier secret for its scheme
+    MissingNullifierSecret(usize),
+    /// The claim at this index repeats the nullifier of an earlier one
+    DuplicateNullifier(usize),
+    /// Proving or verifying the receipt failed
+    Prover(String),
+    /// The journal is not a packed `BatchClaimOutput`
+    InvalidJournal,
+}
+
+impl fmt::Display for BatchProveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchProveError::Empty => write!(f, "batch has no claims"),
+            BatchProveError::MissingNullifierSecret(index) => {
+                write!(f, "claim {} is missing its nullifier secret", index)
+            }
+            BatchProveError::DuplicateNullifier(index) => {
+                write!(f, "claim {} repeats an earlier nullifier", index)
+            }
+            BatchProveError::Prover(err) => write!(f, "proving failed: {}", err),
+            BatchProveError::InvalidJournal => write!(f, "journal is not a batch claim output"),
+        }
+    }
+}
+
+impl std::error::Error for BatchProveError {}
+
+/// Prove `claims` against one campaign in a single receipt
+///
+/// `public_inputs.recipient` is ignored; each claim names its own. Duplicate
+/// nullifiers are rejected before proving, since the guest would abort on them.
+pub fn prove_batch(
+    claims: &[BatchClaim],
+    public_inputs: &PublicInputs,
+) -> Result<(Receipt, BatchClaimOutput), BatchProveError> {
+    check_nullifiers(claims)?;
+
+    let env = ExecutorEnv::builder()
+        .write(&claims)
+        .and_then(|builder| builder.write(public_inputs))
+        .and_then(|builder| builder.build())
+        .map_err(|err| BatchProveError::Prover(err.to_string()))?;
+    let receipt = default_prover()
+        .prove(env, BATCH_CLAIMS_ELF)
+        .map_err(|err| BatchProveError::Prover(err.to_string()))?
+        .receipt;
+    receipt
+        .verify(BATCH_CLAIMS_ID)
+        .map_err(|err| BatchProveError::Prover(err.to_string()))?;
+
+    let output = BatchClaimOutput::from_abi_bytes(&receipt.journal.bytes)
+        .ok_or(BatchProveError::InvalidJournal)?;
+    Ok((receipt, output))
+}
+
+/// Prove `claims` in receipts of at most `batch_size` claims each
+///
+/// Nullifiers are checked across all claims first, so no receipt repeats a
+/// nullifier proven in another.
+pub fn prove_batches(
+    claims: &[BatchClaim],
+    public_inputs: &PublicInputs,
+    batch_size: usize,
+) -> Result<Vec<(Receipt, BatchClaimOutput)>, BatchProveError> {
+    check_nullifiers(claims)?;
+    claims
+        .chunks(batch_size.max(1))
+        .map(|batch| prove_batch(batch, public_inputs))
+        .collect()
+}
+
+/// Check that the batch is non-empty and every claim has a distinct nullifier
+pub fn check_nullifiers(claims: &[BatchClaim]) -> Result<(), BatchProveError> {
+    if claims.is_empty() {
+        return Err(BatchProveError::Empty);
+    }
+
+    let mut nullifiers = HashSet::with_capacity(claims.len());
+    for (index, BatchClaim { input, .. }) in claims.iter().enumerate() {
+        let nullifier = input
+            .nullifier_scheme
+            .compute_nullifier(
+                &input.user_address,
+                input.nullifier_secret.as_ref(),
+                input.epoch_id,
+            )
+            .ok_or(BatchProveError::MissingNullifierSecret(index))?;
+        if !nullifiers.insert(nullifier) {
+            return Err(BatchProveError::DuplicateNullifier(index));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{ClaimInput, NullifierScheme};
+
+    fn claim(address: [u8; 20], secret: Option<[u8; 32]>) -> BatchClaim {
+        BatchClaim {
+            input: ClaimInput {
+                user_address: address,
+                amount: 100,
+                basket: Vec::new(),
+                vesting: None,
+                balance: None,
+                merkle_proof: Vec::new(),
+                leaf_index: 0,
+                leaf_count: 1,
+                epoch_id: 1,
+                nullifier_scheme: NullifierScheme::SecretV2,
+                nullifier_secret: secret,
+                ownership_signature: None,
+                exclusion_proof: None,
+                root_index: None,
+                list_proofs: Vec::new(),
+            },
+            recipient: [0xaa; 20],
+        }
+    }
+
+    #[test]
+    fn test_check_nullifiers() {
+        let claims = vec![
+            claim([1u8; 20], Some([1u8; 32])),
+            claim([2u8; 20], Some([2u8; 32])),
+        ];
+        assert!(check_nullifiers(&claims).is_ok());
+        assert!(matches!(check_nullifiers(&[]), Err(BatchProveError::Empty)));
+
+        // The same secret nullifies the same allocation whatever the address
+        let mut repeated = claims.clone();
+        repeated.push(claim([3u8; 20], Some([1u8; 32])));
+        assert!(matches!(
+            check_nullifiers(&repeated),
+            Err(BatchProveError::DuplicateNullifier(2))
+        ));
+
+        let missing = [claim([1u8; 20], None)];
+        assert!(matches!(
+            check_nullifiers(&missing),
+            Err(BatchProveError::MissingNullifierSecret(0))
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use core::{commit_root, compute_leaf, hash_pair, MerkleHasher, Sha256Hasher, EMPTY_LEAF};
+use memmap2::{Mmap, MmapMut};
+
+use crate::standard::parse_address;
+use crate::tree_file::{checksum, Header, Layout, HEADER_LEN};
+
+/// Errors from building or opening a disk-backed tree
+#[derive(Debug)]
+pub enum DiskTreeError {
+    /// Reading or writing the tree file failed
+    Io(io::Error),
+    /// No leaves were added
+    Empty,
+    /// More leaves than a `u32` leaf index can address
+    TooManyLeaves,
+    /// The line (1-based) of an allocation list is not `address,amount`
+    InvalidLine(usize),
+    /// The file is not a tree file for this hasher, or it is cut short
+    InvalidFile,
+}
+
+impl fmt::Display for DiskTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskTreeError::Io(err) => write!(f, "tree file error: {}", err),
+            DiskTreeError::Empty => write!(f, "cannot build tree from empty list"),
+            DiskTreeError::TooManyLeaves => write!(f, "too many leaves for a u32 index"),
+            DiskTreeError::InvalidLine(line) => write!(f, "invalid allocation on line {}", line),
+            DiskTreeError::InvalidFile => write!(f, "not a valid tree file"),
+        }
+    }
+}
+
+impl std::error::Error for DiskTreeError {}
+
+impl From<io::Error> for DiskTreeError {
+    fn from(err: io::Error) -> Self {
+        DiskTreeError::Io(err)
+    }
+}
+
+/// Streams leaves into a tree file, then hashes the levels above them in place
+///
+/// Only a write buffer is held in memory; the levels are hashed through a
+/// memory map, so the OS pages them in and out as needed.
+pub struct DiskTreeBuilder<H: MerkleHasher = Sha256Hasher> {
+    path: PathBuf,
+    file: BufWriter<File>,
+    leaf_count: u64,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> DiskTreeBuilder<H> {
+    /// Create, or truncate, the tree file at `path`
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, DiskTreeError> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        let mut file = BufWriter::new(file);
+
+        // The header is filled in once the root is known
+        file.write_all(&[0u8; HEADER_LEN])?;
+
+        Ok(DiskTreeBuilder {
+            path,
+            file,
+            leaf_count: 0,
+            _hasher: PhantomData,
+        })
+    }
+
+    /// Append a precomputed leaf hash
+    pub fn push_leaf(&mut self, leaf: &[u8; 32]) -> Result<(), DiskTreeError> {
+        if self.leaf_count == u32::MAX as u64 {
+            return Err(DiskTreeError::TooManyLeaves);
+        }
+        self.file.write_all(leaf)?;
+        self.leaf_count += 1;
+        Ok(())
+    }
+
+    /// Append the leaf of an `(address, amount)` allocation
+    pub fn push_allocation(
+        &mut self,
+        address: &[u8; 20],
+        amount: u128,
+    ) -> Result<(), DiskTreeError> {
+        self.push_leaf(&compute_leaf::<H>(address, amount))
+    }
+
+    /// Number of leaves added so far
+    pub fn leaf_count(&self) -> u32 {
+        self.leaf_count as u32
+    }
+
+    /// Hash every level into the file and open it read-only to serve proofs
+    pub fn finish(self) -> Result<DiskMerkleTree<H>, DiskTreeError> {
+        if self.leaf_count == 0 {
+            return Err(DiskTreeError::Empty);
+        }
+        let file = self.file.into_inner().map_err(|err| err.into_error())?;
+        let layout = Layout::new(self.leaf_count, 0);
+        file.set_len(layout.file_len() as u64)?;
+
+        // SAFETY: the file was created by this builder and nothing else maps it
+        // until the build is done
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        let mut empty = EMPTY_LEAF;
+        for level in 0..layout.depth() {
+            let (below, above) = map.split_at_mut(layout.offsets[level + 1]);
+            let below = &below[layout.offsets[level]..];
+            for i in 0..layout.lens[level + 1] {
+                let left = node_at(below, 2 * i).expect("Every node has a left child");
+                let right = node_at(below, 2 * i + 1).unwrap_or(empty);
+                above[i * 32..(i + 1) * 32].copy_from_slice(&hash_pair::<H>(&left, &right));
+            }
+            empty = hash_pair::<H>(&empty, &empty);
+        }
+
+        let top = node_at(&map[layout.offsets[layout.depth()]..], 0).expect("Top node is written");
+        let root = commit_root::<H>(&top, self.leaf_count as u32);
+        let header = Header {
+            leaf_count: self.leaf_count,
+            root,
+            index_len: 0,
+        };
+        map[..HEADER_LEN].copy_from_slice(&header.encode::<H>());
+        let checksum_offset = layout.checksum_offset();
+        let contents_checksum = checksum([&map[..checksum_offset]]);
+        map[checksum_offset..].copy_from_slice(&contents_checksum);
+        map.flush()?;
+        drop(map);
+
+        DiskMerkleTree::open(&self.path)
+    }
+}
+
+/// A Merkle tree served from a memory-mapped tree file
+///
+/// Roots and proofs match a `MerkleTree` built from the same leaves.
+pub struct DiskMerkleTree<H: MerkleHasher = Sha256Hasher> {
+    map: Mmap,
+    layout: Layout,
+    root: [u8; 32],
+    /// Root of an empty subtree at each level, starting with `EMPTY_LEAF`
+    empty_nodes: Vec<[u8; 32]>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> DiskMerkleTree<H> {
+    /// Build a tree file at `path` from a stream of `(address, amount)` allocations
+    pub fn from_allocations<I>(
+        path: impl AsRef<Path>,
+        allocations: I,
+    ) -> Result<Self, DiskTreeError>
+    where
+        I: IntoIterator<Item = ([u8; 20], u128)>,
+    {
+        let mut builder = DiskTreeBuilder::<H>::create(path)?;
+        for (address, amount) in allocations {
+            builder.push_allocation(&address, amount)?;
+        }
+        builder.finish()
+    }
+
+    /// Build a tree file at `path` from `0x`-prefixed address and decimal
+    /// amount pairs, one `address,amount` per line; blank lines are skipped
+    pub fn from_csv(path: impl AsRef<Path>, reader: impl BufRead) -> Result<Self, DiskTreeError> {
+        let mut builder = DiskTreeBuilder::<H>::create(path)?;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (address, amount) = line
+                .split_once(',')
+                .and_then(|(address, amount)| {
+                    Some((parse_address(address.trim())?, amount.trim().parse().ok()?))
+                })
+                .ok_or(DiskTreeError::InvalidLine(i + 1))?;
+            builder.push_allocation(&address, amount)?;
+        }
+        builder.finish()
+    }
+
+    /// Open a tree file read-only
+    ///
+    /// The header, file length and top node are checked; the levels below are
+    /// trusted until `verify_checksum`, and the file must not be modified
+    /// while it is served. Files written by `MerkleTree::save` open too; their
+    /// address index is skipped.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DiskTreeError> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only and the file is not modified while mapped
+        let map = unsafe { Mmap::map(&file)? };
+        let Header {
+            leaf_count,
+            root,
+            index_len,
+        } = Header::decode::<H>(&map).map_err(|_| DiskTreeError::InvalidFile)?;
+        let layout = Layout::new(leaf_count, index_len);
+        if map.len() != layout.file_len() {
+            return Err(DiskTreeError::InvalidFile);
+        }
+
+        let top =
+            node_at(&map[layout.offsets[layout.depth()]..], 0).ok_or(DiskTreeError::InvalidFile)?;
+        if commit_root::<H>(&top, leaf_count as u32) != root {
+            return Err(DiskTreeError::InvalidFile);
+        }
+
+        let mut empty_nodes = vec![EMPTY_LEAF];
+        for level in 0..layout.depth() {
+            empty_nodes.push(hash_pair::<H>(&empty_nodes[level], &empty_nodes[level]));
+        }
+
+        Ok(DiskMerkleTree {
+            map,
+            layout,
+            root,
+            empty_nodes,
+            _hasher: PhantomData,
+        })
+    }
+
+    /// Check the checksum, reading the whole file once
+    pub fn verify_checksum(&self) -> bool {
+        let offset = self.layout.checksum_offset();
+        checksum([&self.map[..offset]]) == self.map[offset..]
+    }
+
+    /// Get the Merkle proof for a given index
+    pub fn get_proof(&self, index: usize) -> Vec<[u8; 32]> {
+        assert!(index < self.layout.lens[0], "Index out of bounds");
+
+        (0..self.layout.depth())
+            .map(|height| {
+                let sibling = (index >> height) ^ 1;
+                node_at(self.level(height), sibling).unwrap_or(self.empty_nodes[height])
+            })
+            .collect()
+    }
+
+    /// Leaf hash at `index`
+    pub fn leaf(&self, index: usize) -> Option<[u8; 32]> {
+        node_at(self.level(0), index)
+    }
+
+    /// Number of leaves in the tree
+    pub fn leaf_count(&self) -> u32 {
+        self.layout.lens[0] as u32
+    }
+
+    /// Get the root hash
+    pub fn root(&self) -> [u8; 32] {
+        self.root
+    }
+
+    /// Nodes of level `height`, leaves at 0
+    fn level(&self, height: usize) -> &[u8] {
+        let start = self.layout.offsets[height];
+        &self.map[start..start + self.layout.lens[height] * 32]
+    }
+}
+
+/// Node `index` of a level stored as consecutive 32-byte hashes
+fn node_at(level: &[u8], index: usize) -> Option<[u8; 32]> {
+    let start = index.checked_mul(32)?;
+    level
+        .get(start..start + 32)
+        .map(|node| node.try_into().expect("Node is 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+    use core::Keccak256Hasher;
+
+    fn allocations(count: u8) -> Vec<([u8; 20], u128)> {
+        (0..count).map(|i| ([i; 20], 100 + i as u128)).collect()
+    }
+
+    #[test]
+    fn test_disk_tree_matches_memory_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        for count in [1, 2, 3, 5, 8, 9] {
+            let path = dir.path().join(format!("tree-{}.bin", count));
+            let allocations = allocations(count);
+            let disk = DiskMerkleTree::<Sha256Hasher>::from_allocations(&path, allocations.clone())
+                .unwrap();
+            let memory = MerkleTree::<Sha256Hasher>::new(&allocations);
+
+            assert_eq!(disk.root(), memory.root());
+            assert_eq!(disk.leaf_count(), memory.leaf_count());
+            for i in 0..allocations.len() {
+                assert_eq!(disk.leaf(i), Some(memory.leaves[i]));
+                assert_eq!(disk.get_proof(i), memory.get_proof(i));
+            }
+
+            // Reopening serves the same tree
+            let reopened = DiskMerkleTree::<Sha256Hasher>::open(&path).unwrap();
+            assert!(reopened.verify_checksum());
+            assert_eq!(reopened.root(), memory.root());
+            assert_eq!(
+                reopened.get_proof(count as usize - 1),
+                memory.get_proof(count as usize - 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_disk_tree_from_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.bin");
+        let csv = format!(
+            "0x{},100\n\n0x{}, 200\n",
+            hex::encode([1u8; 20]),
+            hex::encode([2u8; 20])
+        );
+        let tree = DiskMerkleTree::<Keccak256Hasher>::from_csv(&path, csv.as_bytes()).unwrap();
+        let memory = MerkleTree::<Keccak256Hasher>::new(&[([1u8; 20], 100), ([2u8; 20], 200)]);
+        assert_eq!(tree.root(), memory.root());
+
+        // Files are bound to the hasher they were built with
+        assert!(matches!(
+            DiskMerkleTree::<Sha256Hasher>::open(&path),
+            Err(DiskTreeError::InvalidFile)
+        ));
+
+        let bad = format!("0x{},100\n0x1234,5\n", hex::encode([1u8; 20]));
+        assert!(matches!(
+            DiskMerkleTree::<Sha256Hasher>::from_csv(&path, bad.as_bytes()),
+            Err(DiskTreeError::InvalidLine(2))
+        ));
+        assert!(matches!(
+            DiskMerkleTree::<Sha256Hasher>::from_csv(&path, "".as_bytes()),
+            Err(DiskTreeError::Empty)
+        ));
+    }
+}
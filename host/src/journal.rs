@@ -0,0 +1,79 @@
+use std::fmt;
+
+use core::ClaimOutput;
+use methods::GUEST_CODE_FOR_ZK_PROOF_ID;
+use risc0_zkvm::Receipt;
+
+/// Errors from verifying a claim receipt and its journal
+#[derive(Debug)]
+pub enum JournalError {
+    /// The receipt does not verify against the claim guest
+    InvalidReceipt(String),
+    /// The journal is not a packed `ClaimOutput`
+    InvalidJournal,
+    /// The claim cannot be submitted before this Unix time
+    NotYetValid(u64),
+    /// The claim could only be submitted until this Unix time
+    Expired(u64),
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalError::InvalidReceipt(err) => write!(f, "invalid claim receipt: {}", err),
+            JournalError::InvalidJournal => write!(f, "journal is not a claim output"),
+            JournalError::NotYetValid(not_before) => {
+                write!(f, "claim is not valid before {}", not_before)
+            }
+            JournalError::Expired(expires_at) => write!(f, "claim expired at {}", expires_at),
+        }
+    }
+}
+
+impl std::error::Error for JournalError {}
+
+/// Verify a claim receipt, decode its journal and check the committed
+/// validity window against `now` (Unix seconds)
+pub fn verify_claim_journal(receipt: &Receipt, now: u64) -> Result<ClaimOutput, JournalError> {
+    receipt
+        .verify(GUEST_CODE_FOR_ZK_PROOF_ID)
+        .map_err(|err| JournalError::InvalidReceipt(err.to_string()))?;
+    let output =
+        ClaimOutput::from_abi_bytes(&receipt.journal.bytes).ok_or(JournalError::InvalidJournal)?;
+    check_window(output.not_before, output.expires_at, now)?;
+    Ok(output)
+}
+
+/// Check that `now` lies in a committed window; zero bounds are open, like on-chain
+pub fn check_window(not_before: u64, expires_at: u64, now: u64) -> Result<(), JournalError> {
+    if now < not_before {
+        return Err(JournalError::NotYetValid(not_before));
+    }
+    if expires_at != 0 && now > expires_at {
+        return Err(JournalError::Expired(expires_at));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_window() {
+        assert!(check_window(0, 0, 1_700_000_000).is_ok());
+        assert!(check_window(100, 200, 100).is_ok());
+        assert!(check_window(100, 200, 200).is_ok());
+        assert!(matches!(
+            check_window(100, 200, 99),
+            Err(JournalError::NotYetValid(100))
+        ));
+        assert!(matches!(
+            check_window(100, 200, 201),
+            Err(JournalError::Expired(200))
+        ));
+
+        // Without an expiry the claim stays valid
+        assert!(check_window(100, 0, u64::MAX).is_ok());
+    }
+}
@@ -1,3 +1,22 @@
+pub mod aggregate;
+pub mod batch;
+pub mod disk;
+pub mod journal;
 pub mod merkle;
+pub mod ownership;
+pub mod relayer;
+pub mod sparse;
+pub mod standard;
+pub mod tiers;
+pub mod tree_file;
 
+pub use aggregate::{AggregateError, ClaimAggregator};
+pub use batch::{prove_batch, prove_batches, BatchProveError};
+pub use disk::{DiskMerkleTree, DiskTreeBuilder, DiskTreeError};
+pub use journal::{verify_claim_journal, JournalError};
 pub use merkle::MerkleTree;
+pub use ownership::ClaimSigner;
+pub use relayer::{prove_relayed_claim, RelayError, RelayerPackage};
+pub use sparse::SparseMerkleTree;
+pub use standard::StandardMerkleTree;
+pub use tree_file::TreeFileError;
@@ -1,9 +1,13 @@
 // These constants represent the RISC-V ELF and the image ID generated by risc0-build.
 // The ELF is used for proving and the ID is used for verification.
-use methods::{GUEST_CODE_FOR_ZK_PROOF_ELF, GUEST_CODE_FOR_ZK_PROOF_ID};
+use core::ownership::ClaimTerms;
+use core::{
+    compute_secret_commitment, ClaimInput, HashScheme, MerkleHasher, PublicInputs, Sha256Hasher,
+};
+use host::{verify_claim_journal, ClaimSigner, MerkleTree};
+use methods::GUEST_CODE_FOR_ZK_PROOF_ELF;
 use risc0_zkvm::{default_prover, ExecutorEnv};
-use host::MerkleTree;
-use core::{ClaimInput, ClaimOutput, PublicInputs};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn main() {
     // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
@@ -13,44 +17,121 @@ fn main() {
 
     println!("=== ZK Airdrop - Phase 2 Demo ===\n");
 
-    // Step 1: Create eligibility list (sample addresses)
-    let eligible_addresses = vec![
-        [1u8; 20],
-        [2u8; 20],
-        [3u8; 20],
-        [4u8; 20],
-    ];
-
-    println!("Created eligibility list with {} addresses", eligible_addresses.len());
-
-    // Step 2: Build Merkle tree
-    let tree = MerkleTree::new(&eligible_addresses);
+    // Step 1: Create eligibility list (sample addresses backed by demo keys)
+    let signers: Vec<ClaimSigner> = (1..=4u8)
+        .map(|i| ClaimSigner::PrivateKey([i; 32]))
+        .collect();
+    let eligible_addresses: Vec<[u8; 20]> = signers
+        .iter()
+        .map(|signer| signer.address().unwrap())
+        .collect();
+
+    println!(
+        "Created eligibility list with {} addresses",
+        eligible_addresses.len()
+    );
+
+    // Each address gets its own allocation (in wei)
+    let amounts: Vec<u128> = (1..=eligible_addresses.len() as u128)
+        .map(|i| i * 1_000_000_000_000_000_000)
+        .collect();
+
+    // Each user keeps a nullifier secret and only publishes its commitment
+    // (fixed here for the demo; real users should sample it randomly)
+    let secrets: Vec<[u8; 32]> = (0..eligible_addresses.len())
+        .map(|i| [0x40 + i as u8; 32])
+        .collect();
+    let entries: Vec<([u8; 20], u128, [u8; 32])> = eligible_addresses
+        .iter()
+        .zip(&amounts)
+        .zip(&secrets)
+        .map(|((addr, amount), secret)| (*addr, *amount, compute_secret_commitment(secret)))
+        .collect();
+
+    // Step 2: Build the Merkle tree and serve proofs from the saved copy when it
+    // has the same root; otherwise (first run, another list, an unreadable
+    // file) save the new tree over it
+    let tree_path = std::env::temp_dir().join("zkairdrop-tree.bin");
+    let built = MerkleTree::<Sha256Hasher>::with_commitments(&entries);
+    let tree = match MerkleTree::<Sha256Hasher>::load(&tree_path) {
+        Ok(tree) if tree.root() == built.root() => {
+            println!("Tree loaded from {}", tree_path.display());
+            tree
+        }
+        loaded => {
+            match loaded {
+                Ok(_) => println!("Saved tree is for another list, rebuilding"),
+                Err(err) => println!("No usable saved tree ({}), building", err),
+            }
+            built.save(&tree_path).expect("Failed to save tree");
+            println!("Tree saved to {}", tree_path.display());
+            built
+        }
+    };
     let root = tree.root();
     println!("Merkle root: 0x{}", hex::encode(root));
 
     // Step 3: User wants to claim (let's say user at index 2)
     let user_index = 2;
     let user_address = eligible_addresses[user_index];
-    let merkle_proof = tree.get_proof(user_index);
+    let (leaf_index, merkle_proof) = tree
+        .proof_for_address(&user_address)
+        .expect("Address is not in the tree");
 
     println!("\nUser claiming:");
     println!("  Address: 0x{}", hex::encode(user_address));
-    println!("  Index: {}", user_index);
+    println!("  Index: {}", leaf_index);
+    println!("  Amount: {}", amounts[user_index]);
     println!("  Proof length: {}", merkle_proof.len());
 
-    // Step 4: Create claim input (private data)
+    // The reward goes to a fresh payout address, unlinked from the eligible one
+    let recipient = [0xaau8; 20];
+    println!("  Recipient: 0x{}", hex::encode(recipient));
+
     let epoch_id = 1u64;
-    let claim_input = ClaimInput {
-        user_address,
-        merkle_proof,
-        leaf_index: user_index as u32,
-        epoch_id,
-    };
+    let campaign_id = [0x01u8; 32];
 
-    // Step 5: Create public inputs
+    // Step 4: Create public inputs
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is before the Unix epoch")
+        .as_secs();
     let public_inputs = PublicInputs {
         merkle_root: root,
         epoch_id,
+        recipient,
+        campaign_id,
+        // Deployment the proof is valid for (a placeholder address for the demo)
+        chain_id: 1,
+        verifying_contract: [0xc0u8; 20],
+        require_ownership: true,
+        hash_scheme: Sha256Hasher::SCHEME,
+        timestamp: now,
+        claimed_amount: 0,
+        min_balance: None,
+        tiers: None,
+        exclusion_root: None,
+        root_set: None,
+        list_policy: None,
+        relayer: [0u8; 20],
+        fee: 0,
+        // The proof has to be submitted within an hour
+        not_before: None,
+        expires_at: Some(now + 3600),
+    };
+
+    // The eligible address signs the claim's terms so nobody else can prove it
+    let ownership_signature = signers[user_index]
+        .sign_claim(&user_address, &ClaimTerms::new(&public_inputs, &recipient))
+        .expect("Failed to sign claim");
+
+    // Step 5: Create claim input (private data)
+    let claim_input = ClaimInput {
+        nullifier_secret: Some(secrets[user_index]),
+        ownership_signature: Some(ownership_signature),
+        ..tree
+            .claim_input(&user_address, epoch_id)
+            .expect("Address is not in the tree")
     };
 
     println!("\nGenerating proof...");
@@ -71,21 +152,31 @@ fn main() {
 
     println!("✓ Proof generated successfully!");
 
-    // Step 8: Extract and verify output
-    let output: ClaimOutput = receipt.journal.decode().unwrap();
+    // Step 8: Verify the receipt, extract the output and check its validity window
+    let output = verify_claim_journal(&receipt, now).expect("Invalid claim receipt");
+    println!("✓ Receipt verified successfully!");
 
     println!("\nProof output:");
     println!("  Verified root: 0x{}", hex::encode(output.merkle_root));
     println!("  Nullifier: 0x{}", hex::encode(output.nullifier));
     println!("  Epoch: {}", output.epoch_id);
+    println!("  Recipient: 0x{}", hex::encode(output.recipient));
+    println!("  Ownership verified: {}", output.ownership_verified);
+    println!("  Hash scheme: {:?}", output.hash_scheme);
+    println!("  Amount: {}", output.amount);
+    println!("  Expires at: {}", output.expires_at);
 
-    // Step 9: Verify the receipt
-    receipt.verify(GUEST_CODE_FOR_ZK_PROOF_ID).unwrap();
-    println!("\n✓ Receipt verified successfully!");
-
-    // Step 10: Verify output matches expected values
+    // Step 9: Verify output matches expected values
     assert_eq!(output.merkle_root, root, "Root mismatch");
     assert_eq!(output.epoch_id, epoch_id, "Epoch mismatch");
+    assert_eq!(output.recipient, recipient, "Recipient mismatch");
+    assert!(output.ownership_verified, "Ownership not proven");
+    assert_eq!(
+        output.hash_scheme,
+        HashScheme::Sha256,
+        "Hash scheme mismatch"
+    );
+    assert_eq!(output.amount, amounts[user_index], "Amount mismatch");
 
     println!("\n=== Phase 2 Complete! ===");
     println!("✓ Merkle tree built");
@@ -93,14 +184,3 @@ fn main() {
     println!("✓ Proof verified");
     println!("✓ Nullifier computed");
 }
-
-// Helper module for hex encoding
-mod hex {
-    pub fn encode(bytes: impl AsRef<[u8]>) -> String {
-        bytes
-            .as_ref()
-            .iter()
-            .map(|b| format!("{:02x}", b))
-            .collect()
-    }
-}
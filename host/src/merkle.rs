@@ -1,95 +1,565 @@
-use core::{compute_leaf, hash_pair};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::iter;
+use std::marker::PhantomData;
+use std::path::Path;
 
-/// A Merkle tree for storing addresses
-pub struct MerkleTree {
+use core::allocation::BASKET_ENTRY_LEN;
+use core::{
+    commit_root, compute_allocation_leaf, compute_balance_leaf, compute_committed_leaf,
+    compute_leaf, hash_pair, AllocationRecord, ClaimInput, HashScheme, ListProof, MerkleHasher,
+    NullifierScheme, Sha256Hasher, TokenAllocation, VestingSchedule, EMPTY_LEAF,
+};
+use serde::Serialize;
+
+use crate::tree_file::{
+    checksum, Header, Layout, TreeFileError, CHECKSUM_LEN, HEADER_LEN, VERSION,
+};
+
+/// A Merkle tree for storing `(address, amount)` allocations, hashed with `H`
+///
+/// Every level is kept after the build, so proofs are plain lookups. Levels
+/// are not padded: a missing right sibling is the root of an all-`EMPTY_LEAF`
+/// subtree, which is the same at every position of a level.
+pub struct MerkleTree<H: MerkleHasher = Sha256Hasher> {
     /// All leaves in the tree
     pub leaves: Vec<[u8; 32]>,
     /// The Merkle root
     pub root: [u8; 32],
+    /// Levels above the leaves, ending with the single top node
+    nodes: Vec<Vec<[u8; 32]>>,
+    /// Root of an empty subtree at each level, starting with `EMPTY_LEAF`
+    empty_nodes: Vec<[u8; 32]>,
+    /// Leaf of each address, when the tree was built from addresses
+    index: HashMap<[u8; 20], IndexEntry>,
+    _hasher: PhantomData<H>,
+}
+
+/// Position of an address and what its leaf commits to
+struct IndexEntry {
+    leaf_index: u32,
+    amount: u128,
+    basket: Vec<TokenAllocation>,
+    vesting: Option<VestingSchedule>,
+    balance: Option<u128>,
+    /// Secret commitment of a `NullifierScheme::SecretV2` leaf
+    commitment: Option<[u8; 32]>,
 }
 
-impl MerkleTree {
-    /// Build a Merkle tree from a list of addresses
-    pub fn new(addresses: &[[u8; 20]]) -> Self {
-        assert!(!addresses.is_empty(), "Cannot build tree from empty list");
+/// Flags of an encoded `IndexEntry`, one per optional field
+const INDEX_VESTING: u8 = 1;
+const INDEX_BALANCE: u8 = 2;
+const INDEX_COMMITMENT: u8 = 4;
+
+impl IndexEntry {
+    fn allocation(leaf_index: usize, amount: u128, commitment: Option<[u8; 32]>) -> Self {
+        IndexEntry {
+            leaf_index: leaf_index as u32,
+            amount,
+            basket: Vec::new(),
+            vesting: None,
+            balance: None,
+            commitment,
+        }
+    }
+
+    fn nullifier_scheme(&self) -> NullifierScheme {
+        match self.commitment {
+            Some(_) => NullifierScheme::SecretV2,
+            None => NullifierScheme::AddressV1,
+        }
+    }
+
+    /// Leaf of `address` with this entry, or `None` if the basket repeats a token
+    fn leaf<H: MerkleHasher>(&self, address: &[u8; 20]) -> Option<[u8; 32]> {
+        let commitment = self.commitment.as_ref();
+        match self.balance {
+            Some(balance) => Some(compute_balance_leaf::<H>(address, balance, commitment)),
+            None => compute_allocation_leaf::<H>(
+                address,
+                self.amount,
+                self.vesting.as_ref(),
+                &self.basket,
+                commitment,
+            ),
+        }
+    }
+
+    /// Append the entry to a tree file index: leaf index (LE), address, amount
+    /// (LE), flags, the flagged vesting schedule, balance (LE) and commitment,
+    /// then the basket length (LE) and packed basket entries
+    fn encode(&self, address: &[u8; 20], out: &mut Vec<u8>) {
+        let flags = (self.vesting.is_some() as u8 * INDEX_VESTING)
+            | (self.balance.is_some() as u8 * INDEX_BALANCE)
+            | (self.commitment.is_some() as u8 * INDEX_COMMITMENT);
+        out.extend_from_slice(&self.leaf_index.to_le_bytes());
+        out.extend_from_slice(address);
+        out.extend_from_slice(&self.amount.to_le_bytes());
+        out.push(flags);
+        if let Some(vesting) = &self.vesting {
+            out.extend_from_slice(&vesting.to_bytes());
+        }
+        if let Some(balance) = self.balance {
+            out.extend_from_slice(&balance.to_le_bytes());
+        }
+        if let Some(commitment) = &self.commitment {
+            out.extend_from_slice(commitment);
+        }
+        out.extend_from_slice(&(self.basket.len() as u32).to_le_bytes());
+        for entry in &self.basket {
+            out.extend_from_slice(&entry.to_abi_bytes());
+        }
+    }
+
+    /// Decode an entry written by `encode` from the front of `bytes`
+    fn decode(bytes: &mut &[u8]) -> Option<([u8; 20], Self)> {
+        let leaf_index = u32::from_le_bytes(take(bytes)?);
+        let address = take(bytes)?;
+        let amount = u128::from_le_bytes(take(bytes)?);
+        let [flags] = take(bytes)?;
+        if flags & !(INDEX_VESTING | INDEX_BALANCE | INDEX_COMMITMENT) != 0 {
+            return None;
+        }
+        let vesting = match flags & INDEX_VESTING {
+            0 => None,
+            _ => Some(VestingSchedule {
+                start: u64::from_be_bytes(take(bytes)?),
+                cliff: u64::from_be_bytes(take(bytes)?),
+                duration: u64::from_be_bytes(take(bytes)?),
+            }),
+        };
+        let balance = match flags & INDEX_BALANCE {
+            0 => None,
+            _ => Some(u128::from_le_bytes(take(bytes)?)),
+        };
+        let commitment = match flags & INDEX_COMMITMENT {
+            0 => None,
+            _ => Some(take(bytes)?),
+        };
+        let basket_len = u32::from_le_bytes(take(bytes)?);
+        let mut basket = Vec::new();
+        for _ in 0..basket_len {
+            let entry: [u8; BASKET_ENTRY_LEN] = take(bytes)?;
+            basket.push(TokenAllocation::from_abi_bytes(&entry)?);
+        }
+
+        let entry = IndexEntry {
+            leaf_index,
+            amount,
+            basket,
+            vesting,
+            balance,
+            commitment,
+        };
+        Some((address, entry))
+    }
+}
+
+/// Split `N` bytes off the front of `bytes`
+fn take<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, rest) = bytes.split_first_chunk::<N>()?;
+    *bytes = rest;
+    Some(*head)
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Build a Merkle tree from a list of `(address, amount)` allocations
+    pub fn new(allocations: &[([u8; 20], u128)]) -> Self {
+        assert!(!allocations.is_empty(), "Cannot build tree from empty list");
 
         // Compute leaves
-        let leaves: Vec<[u8; 32]> = addresses.iter().map(|addr| compute_leaf(addr)).collect();
+        let leaves = hash_leaves(allocations, |(addr, amount)| {
+            compute_leaf::<H>(addr, *amount)
+        });
+
+        Self::from_leaves(leaves).with_index(
+            allocations
+                .iter()
+                .enumerate()
+                .map(|(i, (addr, amount))| (*addr, IndexEntry::allocation(i, *amount, None))),
+        )
+    }
+
+    /// Build a Merkle tree for `NullifierScheme::SecretV2` from
+    /// `(address, amount, secret commitment)` entries
+    pub fn with_commitments(entries: &[([u8; 20], u128, [u8; 32])]) -> Self {
+        assert!(!entries.is_empty(), "Cannot build tree from empty list");
 
-        // Build tree
-        let root = Self::compute_root(&leaves);
+        let leaves = hash_leaves(entries, |(addr, amount, commitment)| {
+            compute_committed_leaf::<H>(addr, *amount, commitment)
+        });
 
-        MerkleTree { leaves, root }
+        Self::from_leaves(leaves).with_index(entries.iter().enumerate().map(
+            |(i, (addr, amount, commitment))| {
+                (*addr, IndexEntry::allocation(i, *amount, Some(*commitment)))
+            },
+        ))
     }
 
-    /// Compute the Merkle root from leaves
-    fn compute_root(leaves: &[[u8; 32]]) -> [u8; 32] {
-        let mut current_level = leaves.to_vec();
+    /// Build a Merkle tree from allocation records with token baskets
+    pub fn from_records(records: &[AllocationRecord]) -> Self {
+        assert!(!records.is_empty(), "Cannot build tree from empty list");
 
-        while current_level.len() > 1 {
-            let mut next_level = Vec::new();
+        let leaves = hash_leaves(records, |record| {
+            record.leaf::<H>().expect("Duplicate token in basket")
+        });
 
-            for i in (0..current_level.len()).step_by(2) {
-                if i + 1 < current_level.len() {
-                    // Hash pair
-                    let hash = hash_pair(&current_level[i], &current_level[i + 1]);
-                    next_level.push(hash);
-                } else {
-                    // Odd number of nodes, hash with itself
-                    let hash = hash_pair(&current_level[i], &current_level[i]);
-                    next_level.push(hash);
-                }
-            }
+        Self::from_leaves(leaves).with_index(records.iter().enumerate().map(|(i, record)| {
+            let entry = IndexEntry {
+                basket: record.basket.clone(),
+                vesting: record.vesting,
+                ..IndexEntry::allocation(i, record.amount, record.commitment)
+            };
+            (record.address, entry)
+        }))
+    }
+
+    /// Build a balance snapshot tree from `(address, balance)` pairs for
+    /// threshold campaigns
+    pub fn with_balances(balances: &[([u8; 20], u128)]) -> Self {
+        assert!(!balances.is_empty(), "Cannot build tree from empty list");
+
+        let leaves = hash_leaves(balances, |(addr, balance)| {
+            compute_balance_leaf::<H>(addr, *balance, None)
+        });
+
+        Self::from_leaves(leaves).with_index(balances.iter().enumerate().map(
+            |(i, (addr, balance))| {
+                let entry = IndexEntry {
+                    balance: Some(*balance),
+                    ..IndexEntry::allocation(i, 0, None)
+                };
+                (*addr, entry)
+            },
+        ))
+    }
+
+    /// Build a Merkle tree from precomputed leaf hashes; the tree has no
+    /// address index, so lookups by address return `None`
+    pub fn from_leaves(leaves: Vec<[u8; 32]>) -> Self {
+        assert!(!leaves.is_empty(), "Cannot build tree from empty list");
+
+        // Hash level by level up to the next power of two
+        let depth = leaves.len().next_power_of_two().trailing_zeros() as usize;
+        let mut nodes: Vec<Vec<[u8; 32]>> = Vec::with_capacity(depth);
+        let mut empty_nodes = vec![EMPTY_LEAF];
+        for level in 0..depth {
+            let below = nodes.last().unwrap_or(&leaves);
+            let empty = empty_nodes[level];
+            #[cfg(feature = "parallel")]
+            nodes.push(hash_level_parallel::<H>(below, &empty));
+            #[cfg(not(feature = "parallel"))]
+            nodes.push(hash_level::<H>(below, &empty));
+            empty_nodes.push(hash_pair::<H>(&empty, &empty));
+        }
+
+        let top = nodes.last().map_or(leaves[0], |level| level[0]);
+        let root = commit_root::<H>(&top, leaves.len() as u32);
+
+        MerkleTree {
+            leaves,
+            root,
+            nodes,
+            empty_nodes,
+            index: HashMap::new(),
+            _hasher: PhantomData,
+        }
+    }
 
-            current_level = next_level;
+    /// Index addresses by leaf; a repeated address keeps its first leaf
+    fn with_index(mut self, entries: impl Iterator<Item = ([u8; 20], IndexEntry)>) -> Self {
+        for (address, entry) in entries {
+            self.index.entry(address).or_insert(entry);
         }
+        self
+    }
 
-        current_level[0]
+    /// Leaf index of `address`, or `None` if it is not in the tree
+    pub fn index_of(&self, address: &[u8; 20]) -> Option<u32> {
+        self.index.get(address).map(|entry| entry.leaf_index)
+    }
+
+    /// Leaf index and Merkle proof of `address`, or `None` if it is not in the tree
+    pub fn proof_for_address(&self, address: &[u8; 20]) -> Option<(u32, Vec<[u8; 32]>)> {
+        let index = self.index_of(address)?;
+        Some((index, self.get_proof(index as usize)))
+    }
+
+    /// Claim input for `address` in epoch `epoch_id`, or `None` if it is not in the tree
+    ///
+    /// `SecretV2` claimers still set `nullifier_secret`, and campaigns that
+    /// require ownership set `ownership_signature`.
+    pub fn claim_input(&self, address: &[u8; 20], epoch_id: u64) -> Option<ClaimInput> {
+        let entry = self.index.get(address)?;
+        Some(ClaimInput {
+            user_address: *address,
+            amount: entry.amount,
+            basket: entry.basket.clone(),
+            vesting: entry.vesting,
+            balance: entry.balance,
+            merkle_proof: self.get_proof(entry.leaf_index as usize),
+            leaf_index: entry.leaf_index,
+            leaf_count: self.leaf_count(),
+            epoch_id,
+            nullifier_scheme: entry.nullifier_scheme(),
+            nullifier_secret: None,
+            ownership_signature: None,
+            exclusion_proof: None,
+            root_index: None,
+            list_proofs: Vec::new(),
+        })
     }
 
     /// Get the Merkle proof for a given index
     pub fn get_proof(&self, index: usize) -> Vec<[u8; 32]> {
         assert!(index < self.leaves.len(), "Index out of bounds");
 
-        let mut proof = Vec::new();
-        let mut current_level = self.leaves.clone();
-        let mut current_index = index;
-
-        while current_level.len() > 1 {
-            let mut next_level = Vec::new();
-
-            for i in (0..current_level.len()).step_by(2) {
-                if i + 1 < current_level.len() {
-                    let hash = hash_pair(&current_level[i], &current_level[i + 1]);
-                    next_level.push(hash);
-
-                    // Add sibling to proof
-                    if i == current_index {
-                        proof.push(current_level[i + 1]);
-                    } else if i + 1 == current_index {
-                        proof.push(current_level[i]);
-                    }
-                } else {
-                    let hash = hash_pair(&current_level[i], &current_level[i]);
-                    next_level.push(hash);
-
-                    if i == current_index {
-                        proof.push(current_level[i]);
-                    }
-                }
-            }
+        // Siblings on every level below the top node
+        self.levels()
+            .take(self.nodes.len())
+            .zip(&self.empty_nodes)
+            .enumerate()
+            .map(|(height, (level, empty))| {
+                let sibling = (index >> height) ^ 1;
+                level.get(sibling).copied().unwrap_or(*empty)
+            })
+            .collect()
+    }
 
-            current_level = next_level;
-            current_index /= 2;
+    /// Proof that leaf `index`, built by `new` with `amount`, is on list `list`
+    /// of a `ListPolicy`
+    pub fn list_proof(&self, list: u32, index: usize, amount: u128) -> ListProof {
+        ListProof {
+            list,
+            amount,
+            merkle_proof: self.get_proof(index),
+            leaf_index: index as u32,
+            leaf_count: self.leaf_count(),
         }
+    }
 
-        proof
+    /// Number of (unpadded) leaves in the tree
+    pub fn leaf_count(&self) -> u32 {
+        self.leaves.len() as u32
     }
 
     /// Get the root hash
     pub fn root(&self) -> [u8; 32] {
         self.root
     }
+
+    /// Save the tree to a tree file at `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TreeFileError> {
+        self.write_to(File::create(path)?)
+    }
+
+    /// Load a tree file, checking its checksum, that its levels hash to its
+    /// root and that its address index matches the leaves
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TreeFileError> {
+        Self::read_from(File::open(path)?)
+    }
+
+    /// Write the tree file: a header with the hash scheme, leaf count, depth
+    /// and root, every level from the leaves up, the address index by leaf and
+    /// a SHA-256 checksum
+    pub fn write_to(&self, writer: impl Write) -> Result<(), TreeFileError> {
+        let mut entries: Vec<_> = self.index.iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.leaf_index);
+        let mut index = Vec::new();
+        for (address, entry) in entries {
+            entry.encode(address, &mut index);
+        }
+
+        let header = Header {
+            leaf_count: self.leaves.len() as u64,
+            root: self.root,
+            index_len: index.len() as u64,
+        }
+        .encode::<H>();
+        let levels = || self.levels().map(|level| level.as_flattened());
+        let contents = || {
+            iter::once(header.as_slice())
+                .chain(levels())
+                .chain(iter::once(index.as_slice()))
+        };
+        let contents_checksum = checksum(contents());
+
+        let mut writer = BufWriter::new(writer);
+        for part in contents() {
+            writer.write_all(part)?;
+        }
+        writer.write_all(&contents_checksum)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read a tree file written by `write_to`
+    pub fn read_from(reader: impl Read) -> Result<Self, TreeFileError> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0u8; HEADER_LEN];
+        read_exact(&mut reader, &mut header)?;
+        let Header {
+            leaf_count,
+            root,
+            index_len,
+        } = Header::decode::<H>(&header)?;
+
+        let mut levels = Vec::new();
+        for &len in &Layout::new(leaf_count, index_len).lens {
+            levels.push(read_nodes(&mut reader, len)?);
+        }
+        // The index grows as its bytes arrive, like the levels
+        let mut index = Vec::new();
+        reader.by_ref().take(index_len).read_to_end(&mut index)?;
+        if index.len() as u64 != index_len {
+            return Err(TreeFileError::InvalidLength);
+        }
+        let mut stored_checksum = [0u8; CHECKSUM_LEN];
+        read_exact(&mut reader, &mut stored_checksum)?;
+        if reader.read(&mut [0u8; 1])? != 0 {
+            return Err(TreeFileError::InvalidLength);
+        }
+        let contents = iter::once(header.as_slice())
+            .chain(levels.iter().map(|l| l.as_flattened()))
+            .chain(iter::once(index.as_slice()));
+        if checksum(contents) != stored_checksum {
+            return Err(TreeFileError::ChecksumMismatch);
+        }
+
+        // Rehash the leaves rather than trusting the stored levels
+        let mut levels = levels.into_iter();
+        let tree = Self::from_leaves(levels.next().expect("Layout has a leaf level"));
+        if tree.root != root || !tree.nodes.iter().eq(levels.as_slice()) {
+            return Err(TreeFileError::RootMismatch);
+        }
+
+        // Every index entry has to rebuild the leaf it points at
+        let mut bytes = index.as_slice();
+        let mut entries = Vec::new();
+        while !bytes.is_empty() {
+            let (address, entry) =
+                IndexEntry::decode(&mut bytes).ok_or(TreeFileError::InvalidIndex)?;
+            let leaf = tree.leaves.get(entry.leaf_index as usize);
+            if leaf.is_none() || leaf.copied() != entry.leaf::<H>(&address) {
+                return Err(TreeFileError::InvalidIndex);
+            }
+            entries.push((address, entry));
+        }
+        Ok(tree.with_index(entries.into_iter()))
+    }
+
+    /// Export every level as JSON for humans; `load` reads the binary format only
+    pub fn to_json(&self) -> String {
+        let dump = TreeJson {
+            format: format!("zkairdrop-tree-v{}", VERSION),
+            hash_scheme: H::SCHEME,
+            leaf_count: self.leaf_count(),
+            depth: self.nodes.len(),
+            root: format!("0x{}", hex::encode(self.root)),
+            levels: self
+                .levels()
+                .map(|level| {
+                    level
+                        .iter()
+                        .map(|node| format!("0x{}", hex::encode(node)))
+                        .collect()
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&dump).expect("Tree dump is always serializable")
+    }
+
+    /// Levels from the leaves up to the top node
+    fn levels(&self) -> impl Iterator<Item = &Vec<[u8; 32]>> {
+        iter::once(&self.leaves).chain(&self.nodes)
+    }
+}
+
+/// JSON export of a `MerkleTree`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TreeJson {
+    format: String,
+    hash_scheme: HashScheme,
+    leaf_count: u32,
+    depth: usize,
+    root: String,
+    levels: Vec<Vec<String>>,
+}
+
+/// Nodes read per allocation, so a header claiming more leaves than the file
+/// holds fails on the missing bytes instead of allocating for all of them
+const READ_CHUNK_NODES: usize = 1 << 16;
+
+/// Read `len` nodes, growing the level as the bytes arrive
+fn read_nodes(reader: &mut impl Read, len: usize) -> Result<Vec<[u8; 32]>, TreeFileError> {
+    let mut nodes = Vec::new();
+    while nodes.len() < len {
+        let start = nodes.len();
+        nodes.resize(start + (len - start).min(READ_CHUNK_NODES), [0u8; 32]);
+        read_exact(reader, nodes[start..].as_flattened_mut())?;
+    }
+    Ok(nodes)
+}
+
+/// `Read::read_exact`, reporting a file cut short as `InvalidLength`
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<(), TreeFileError> {
+    reader.read_exact(buf).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => TreeFileError::InvalidLength,
+        _ => TreeFileError::Io(err),
+    })
+}
+
+/// Levels shorter than this are hashed on one thread
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_LEN: usize = 1 << 12;
+
+/// Hash each item into a leaf
+#[cfg(not(feature = "parallel"))]
+fn hash_leaves<T, F>(items: &[T], leaf: F) -> Vec<[u8; 32]>
+where
+    F: Fn(&T) -> [u8; 32],
+{
+    items.iter().map(leaf).collect()
+}
+
+/// Hash each item into a leaf on the rayon pool; the order is kept
+#[cfg(feature = "parallel")]
+fn hash_leaves<T, F>(items: &[T], leaf: F) -> Vec<[u8; 32]>
+where
+    T: Sync,
+    F: Fn(&T) -> [u8; 32] + Sync + Send,
+{
+    use rayon::prelude::*;
+
+    items
+        .par_iter()
+        .with_min_len(PARALLEL_MIN_LEN)
+        .map(leaf)
+        .collect()
+}
+
+/// Hash a level into the one above it, pairing a trailing node with `empty`
+fn hash_level<H: MerkleHasher>(below: &[[u8; 32]], empty: &[u8; 32]) -> Vec<[u8; 32]> {
+    below
+        .chunks(2)
+        .map(|pair| hash_pair::<H>(&pair[0], pair.get(1).unwrap_or(empty)))
+        .collect()
+}
+
+/// `hash_level` on the rayon pool
+#[cfg(feature = "parallel")]
+fn hash_level_parallel<H: MerkleHasher>(below: &[[u8; 32]], empty: &[u8; 32]) -> Vec<[u8; 32]> {
+    use rayon::prelude::*;
+
+    if below.len() < PARALLEL_MIN_LEN {
+        return hash_level::<H>(below, empty);
+    }
+    below
+        .par_chunks(2)
+        .map(|pair| hash_pair::<H>(&pair[0], pair.get(1).unwrap_or(empty)))
+        .collect()
 }
 
 #[cfg(test)]
@@ -98,29 +568,588 @@ mod tests {
 
     #[test]
     fn test_merkle_tree_single() {
-        let addresses = vec![[1u8; 20]];
-        let tree = MerkleTree::new(&addresses);
+        let allocations = vec![([1u8; 20], 100)];
+        let tree = MerkleTree::<Sha256Hasher>::new(&allocations);
         assert_ne!(tree.root(), [0u8; 32]);
     }
 
     #[test]
     fn test_merkle_tree_multiple() {
-        let addresses = vec![[1u8; 20], [2u8; 20], [3u8; 20], [4u8; 20]];
-        let tree = MerkleTree::new(&addresses);
+        let allocations = vec![
+            ([1u8; 20], 100),
+            ([2u8; 20], 200),
+            ([3u8; 20], 300),
+            ([4u8; 20], 400),
+        ];
+        let tree = MerkleTree::<Sha256Hasher>::new(&allocations);
         assert_ne!(tree.root(), [0u8; 32]);
     }
 
     #[test]
     fn test_merkle_proof() {
-        let addresses = vec![[1u8; 20], [2u8; 20], [3u8; 20], [4u8; 20]];
-        let tree = MerkleTree::new(&addresses);
+        let allocations = vec![
+            ([1u8; 20], 100),
+            ([2u8; 20], 200),
+            ([3u8; 20], 300),
+            ([4u8; 20], 400),
+        ];
+        let tree = MerkleTree::<Sha256Hasher>::new(&allocations);
 
-        for i in 0..addresses.len() {
+        for (i, (address, amount)) in allocations.iter().enumerate() {
             let proof = tree.get_proof(i);
-            let leaf = compute_leaf(&addresses[i]);
-            let is_valid =
-                core::verify_merkle_proof(&leaf, &proof, i as u32, &tree.root());
+            let leaf = compute_leaf::<Sha256Hasher>(address, *amount);
+            let is_valid = core::verify_merkle_proof::<Sha256Hasher>(
+                &leaf,
+                &proof,
+                i as u32,
+                tree.leaf_count(),
+                &tree.root(),
+            );
             assert!(is_valid, "Proof for index {} should be valid", i);
+
+            // Claiming a different amount fails
+            let inflated = compute_leaf::<Sha256Hasher>(address, amount + 1);
+            assert!(!core::verify_merkle_proof::<Sha256Hasher>(
+                &inflated,
+                &proof,
+                i as u32,
+                tree.leaf_count(),
+                &tree.root(),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_keccak() {
+        use core::Keccak256Hasher;
+
+        let allocations = vec![([1u8; 20], 100), ([2u8; 20], 200), ([3u8; 20], 300)];
+        let tree = MerkleTree::<Keccak256Hasher>::new(&allocations);
+        assert_ne!(
+            tree.root(),
+            MerkleTree::<Sha256Hasher>::new(&allocations).root()
+        );
+
+        for (i, (address, amount)) in allocations.iter().enumerate() {
+            let leaf = compute_leaf::<Keccak256Hasher>(address, *amount);
+            let proof = tree.get_proof(i);
+            assert!(core::verify_merkle_proof::<Keccak256Hasher>(
+                &leaf,
+                &proof,
+                i as u32,
+                tree.leaf_count(),
+                &tree.root(),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_odd_duplication_collision_is_gone() {
+        // [a, b, c] used to hash like [a, b, c, c]
+        let a = ([1u8; 20], 100);
+        let b = ([2u8; 20], 200);
+        let c = ([3u8; 20], 300);
+        let three = MerkleTree::<Sha256Hasher>::new(&[a, b, c]);
+        let four = MerkleTree::<Sha256Hasher>::new(&[a, b, c, c]);
+        assert_ne!(three.root(), four.root());
+
+        // The padding slot of the three-leaf tree cannot be proven
+        let proof = four.get_proof(3);
+        let leaf = compute_leaf::<Sha256Hasher>(&c.0, c.1);
+        assert!(!core::verify_merkle_proof::<Sha256Hasher>(
+            &leaf,
+            &proof,
+            3,
+            3,
+            &three.root()
+        ));
+    }
+
+    #[test]
+    fn test_levels_match_padded_tree() {
+        for count in 1..=9u8 {
+            let leaves: Vec<[u8; 32]> = (0..count)
+                .map(|i| compute_leaf::<Sha256Hasher>(&[i; 20], 100))
+                .collect();
+            let tree = MerkleTree::<Sha256Hasher>::from_leaves(leaves.clone());
+
+            // Pad to a power of two and rehash every level, collecting siblings on the way
+            let mut level = leaves.clone();
+            level.resize(leaves.len().next_power_of_two(), EMPTY_LEAF);
+            let mut proofs = vec![Vec::new(); leaves.len()];
+            while level.len() > 1 {
+                for (index, proof) in proofs.iter_mut().enumerate() {
+                    proof.push(level[(index >> proof.len()) ^ 1]);
+                }
+                level = level
+                    .chunks(2)
+                    .map(|pair| hash_pair::<Sha256Hasher>(&pair[0], &pair[1]))
+                    .collect();
+            }
+
+            assert_eq!(
+                tree.root(),
+                commit_root::<Sha256Hasher>(&level[0], count as u32)
+            );
+            for (index, proof) in proofs.iter().enumerate() {
+                assert_eq!(&tree.get_proof(index), proof);
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_build_is_deterministic() {
+        // Large enough for the leaf level and the two above it to be split across threads
+        let allocations: Vec<([u8; 20], u128)> = (0..5 * PARALLEL_MIN_LEN as u32 + 3)
+            .map(|i| {
+                let mut address = [0u8; 20];
+                address[16..].copy_from_slice(&i.to_be_bytes());
+                (address, i as u128)
+            })
+            .collect();
+        let tree = MerkleTree::<Sha256Hasher>::new(&allocations);
+
+        // Sequential reference build
+        let mut level: Vec<[u8; 32]> = allocations
+            .iter()
+            .map(|(addr, amount)| compute_leaf::<Sha256Hasher>(addr, *amount))
+            .collect();
+        assert_eq!(tree.leaves, level);
+        let mut empty = EMPTY_LEAF;
+        for nodes in &tree.nodes {
+            level = hash_level::<Sha256Hasher>(&level, &empty);
+            assert_eq!(nodes, &level);
+            empty = hash_pair::<Sha256Hasher>(&empty, &empty);
+        }
+        let root = commit_root::<Sha256Hasher>(&level[0], allocations.len() as u32);
+        assert_eq!(tree.root(), root);
+
+        // Rebuilding gives the same tree
+        for _ in 0..3 {
+            assert_eq!(MerkleTree::<Sha256Hasher>::new(&allocations).root(), root);
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        use crate::DiskMerkleTree;
+        use core::Keccak256Hasher;
+
+        let allocations: Vec<([u8; 20], u128)> = (0..5u8).map(|i| ([i; 20], 100)).collect();
+        let tree = MerkleTree::<Sha256Hasher>::new(&allocations);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree.bin");
+        tree.save(&path).unwrap();
+
+        let loaded = MerkleTree::<Sha256Hasher>::load(&path).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        assert_eq!(loaded.leaves, tree.leaves);
+        assert_eq!(loaded.get_proof(4), tree.get_proof(4));
+        assert_eq!(
+            loaded.proof_for_address(&[3u8; 20]),
+            tree.proof_for_address(&[3u8; 20])
+        );
+
+        // The same file can be served from disk
+        let disk = DiskMerkleTree::<Sha256Hasher>::open(&path).unwrap();
+        assert_eq!(disk.get_proof(4), tree.get_proof(4));
+
+        assert!(matches!(
+            MerkleTree::<Keccak256Hasher>::load(&path),
+            Err(TreeFileError::WrongScheme)
+        ));
+
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+        assert!(matches!(
+            MerkleTree::<Sha256Hasher>::read_from(&bytes[..bytes.len() - 1]),
+            Err(TreeFileError::InvalidLength)
+        ));
+
+        // A header claiming more leaves than the file holds is rejected before
+        // the levels are allocated
+        let mut huge = bytes.clone();
+        huge[11] = 32;
+        huge[16..24].copy_from_slice(&(u32::MAX as u64).to_le_bytes());
+        assert!(matches!(
+            MerkleTree::<Sha256Hasher>::read_from(huge.as_slice()),
+            Err(TreeFileError::InvalidLength)
+        ));
+
+        // A corrupted leaf fails the checksum, and with a matching checksum the root check
+        let mut corrupted = bytes.clone();
+        corrupted[HEADER_LEN] ^= 1;
+        assert!(matches!(
+            MerkleTree::<Sha256Hasher>::read_from(corrupted.as_slice()),
+            Err(TreeFileError::ChecksumMismatch)
+        ));
+        let checksum_offset = corrupted.len() - CHECKSUM_LEN;
+        let contents_checksum = checksum([&corrupted[..checksum_offset]]);
+        corrupted[checksum_offset..].copy_from_slice(&contents_checksum);
+        assert!(matches!(
+            MerkleTree::<Sha256Hasher>::read_from(corrupted.as_slice()),
+            Err(TreeFileError::RootMismatch)
+        ));
+
+        let json: serde_json::Value = serde_json::from_str(&tree.to_json()).unwrap();
+        assert_eq!(json["hashScheme"], "Sha256");
+        assert_eq!(json["leafCount"], 5);
+        assert_eq!(json["depth"], 3);
+        assert_eq!(json["root"], format!("0x{}", hex::encode(tree.root())));
+        assert_eq!(json["levels"][3].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_save_and_load_index() {
+        use core::{compute_secret_commitment, NullifierScheme};
+
+        let records: Vec<AllocationRecord> = (0..3u8)
+            .map(|i| AllocationRecord {
+                address: [i; 20],
+                amount: 1000 * (i as u128 + 1),
+                vesting: (i == 1).then_some(VestingSchedule {
+                    start: 100,
+                    cliff: 10,
+                    duration: 1000,
+                }),
+                basket: vec![TokenAllocation {
+                    token: [0xa0 + i; 20],
+                    amount: 5,
+                }],
+                commitment: (i != 2).then(|| compute_secret_commitment(&[i; 32])),
+            })
+            .collect();
+        let tree = MerkleTree::<Sha256Hasher>::from_records(&records);
+        let mut bytes = Vec::new();
+        tree.write_to(&mut bytes).unwrap();
+
+        let loaded = MerkleTree::<Sha256Hasher>::read_from(bytes.as_slice()).unwrap();
+        for record in &records {
+            let address = &record.address;
+            assert_eq!(
+                loaded.proof_for_address(address),
+                tree.proof_for_address(address)
+            );
+            let input = loaded.claim_input(address, 1).unwrap();
+            assert_eq!(input.amount, record.amount);
+            assert_eq!(input.vesting, record.vesting);
+            assert_eq!(input.basket, record.basket);
+        }
+        let input = loaded.claim_input(&[0u8; 20], 1).unwrap();
+        assert_eq!(input.nullifier_scheme, NullifierScheme::SecretV2);
+        let input = loaded.claim_input(&[2u8; 20], 1).unwrap();
+        assert_eq!(input.nullifier_scheme, NullifierScheme::AddressV1);
+
+        let balances = MerkleTree::<Sha256Hasher>::with_balances(&[([7u8; 20], 50)]);
+        let mut bytes = Vec::new();
+        balances.write_to(&mut bytes).unwrap();
+        let loaded = MerkleTree::<Sha256Hasher>::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.claim_input(&[7u8; 20], 1).unwrap().balance, Some(50));
+
+        // An index entry that does not rebuild its leaf is rejected even with
+        // a matching checksum
+        let mut tampered = bytes.clone();
+        let checksum_offset = tampered.len() - CHECKSUM_LEN;
+        tampered[checksum_offset - 1] ^= 1;
+        let contents_checksum = checksum([&tampered[..checksum_offset]]);
+        tampered[checksum_offset..].copy_from_slice(&contents_checksum);
+        assert!(matches!(
+            MerkleTree::<Sha256Hasher>::read_from(tampered.as_slice()),
+            Err(TreeFileError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn test_internal_node_is_not_a_leaf() {
+        let allocations = vec![
+            ([1u8; 20], 100),
+            ([2u8; 20], 200),
+            ([3u8; 20], 300),
+            ([4u8; 20], 400),
+        ];
+        let tree = MerkleTree::<Sha256Hasher>::new(&allocations);
+
+        // Present the left internal node as a leaf with a one-element proof
+        let node = hash_pair::<Sha256Hasher>(&tree.leaves[0], &tree.leaves[1]);
+        let sibling = hash_pair::<Sha256Hasher>(&tree.leaves[2], &tree.leaves[3]);
+        assert!(!core::verify_merkle_proof::<Sha256Hasher>(
+            &node,
+            &[sibling],
+            0,
+            4,
+            &tree.root()
+        ));
+        assert!(!core::verify_merkle_proof::<Sha256Hasher>(
+            &node,
+            &[sibling],
+            0,
+            2,
+            &tree.root()
+        ));
+    }
+
+    #[test]
+    fn test_merkle_proof_with_commitments() {
+        use core::{compute_secret_commitment, NullifierScheme};
+
+        let secrets = [[11u8; 32], [12u8; 32], [13u8; 32]];
+        let entries: Vec<([u8; 20], u128, [u8; 32])> = secrets
+            .iter()
+            .enumerate()
+            .map(|(i, secret)| ([i as u8 + 1; 20], 100, compute_secret_commitment(secret)))
+            .collect();
+        let tree = MerkleTree::<Sha256Hasher>::with_commitments(&entries);
+
+        for (i, secret) in secrets.iter().enumerate() {
+            let proof = tree.get_proof(i);
+            let leaf = NullifierScheme::SecretV2
+                .compute_leaf::<Sha256Hasher>(&entries[i].0, entries[i].1, None, &[], Some(secret))
+                .unwrap();
+            assert!(core::verify_merkle_proof::<Sha256Hasher>(
+                &leaf,
+                &proof,
+                i as u32,
+                3,
+                &tree.root()
+            ));
+
+            // A different secret is not bound to the leaf
+            let wrong = NullifierScheme::SecretV2
+                .compute_leaf::<Sha256Hasher>(
+                    &entries[i].0,
+                    entries[i].1,
+                    None,
+                    &[],
+                    Some(&[0u8; 32]),
+                )
+                .unwrap();
+            assert!(!core::verify_merkle_proof::<Sha256Hasher>(
+                &wrong,
+                &proof,
+                i as u32,
+                3,
+                &tree.root()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_proof_for_address() {
+        use core::{compute_secret_commitment, NullifierScheme};
+
+        let allocations: Vec<([u8; 20], u128)> = (1..=5u8).map(|i| ([i; 20], i as u128)).collect();
+        let tree = MerkleTree::<Sha256Hasher>::new(&allocations);
+        assert_eq!(
+            tree.proof_for_address(&[4u8; 20]),
+            Some((3, tree.get_proof(3)))
+        );
+        assert_eq!(tree.proof_for_address(&[9u8; 20]), None);
+        assert!(tree.claim_input(&[9u8; 20], 1).is_none());
+
+        // Trees built from bare leaves have no addresses to look up
+        let bare = MerkleTree::<Sha256Hasher>::from_leaves(tree.leaves.clone());
+        assert_eq!(bare.index_of(&[4u8; 20]), None);
+
+        let secret = [7u8; 32];
+        let entries = [
+            ([1u8; 20], 100, compute_secret_commitment(&[1u8; 32])),
+            ([2u8; 20], 250, compute_secret_commitment(&secret)),
+        ];
+        let tree = MerkleTree::<Sha256Hasher>::with_commitments(&entries);
+        let input = tree.claim_input(&[2u8; 20], 3).unwrap();
+        assert_eq!(input.amount, 250);
+        assert_eq!(input.epoch_id, 3);
+        assert_eq!(input.nullifier_scheme, NullifierScheme::SecretV2);
+
+        let leaf = input
+            .nullifier_scheme
+            .compute_leaf::<Sha256Hasher>(
+                &input.user_address,
+                input.amount,
+                None,
+                &[],
+                Some(&secret),
+            )
+            .unwrap();
+        assert!(core::verify_merkle_proof::<Sha256Hasher>(
+            &leaf,
+            &input.merkle_proof,
+            input.leaf_index,
+            input.leaf_count,
+            &tree.root()
+        ));
+    }
+
+    #[test]
+    fn test_merkle_proof_with_baskets() {
+        use core::{NullifierScheme, TokenAllocation};
+
+        let token_a = TokenAllocation {
+            token: [0xa0u8; 20],
+            amount: 500,
+        };
+        let nft = TokenAllocation {
+            token: [0xb0u8; 20],
+            amount: 7,
+        };
+        let records = vec![
+            AllocationRecord {
+                address: [1u8; 20],
+                amount: 100,
+                basket: vec![nft, token_a],
+                vesting: None,
+                commitment: None,
+            },
+            AllocationRecord {
+                address: [2u8; 20],
+                amount: 0,
+                basket: vec![token_a],
+                vesting: None,
+                commitment: None,
+            },
+            AllocationRecord {
+                address: [3u8; 20],
+                amount: 300,
+                basket: Vec::new(),
+                vesting: None,
+                commitment: None,
+            },
+        ];
+        let tree = MerkleTree::<Sha256Hasher>::from_records(&records);
+
+        for (i, record) in records.iter().enumerate() {
+            // The claimer may list the basket in any order
+            let mut basket = record.basket.clone();
+            basket.reverse();
+            let leaf = NullifierScheme::AddressV1
+                .compute_leaf::<Sha256Hasher>(&record.address, record.amount, None, &basket, None)
+                .unwrap();
+            let proof = tree.get_proof(i);
+            assert!(core::verify_merkle_proof::<Sha256Hasher>(
+                &leaf,
+                &proof,
+                i as u32,
+                tree.leaf_count(),
+                &tree.root()
+            ));
+        }
+
+        // Dropping a token from the basket breaks the proof
+        let leaf = NullifierScheme::AddressV1
+            .compute_leaf::<Sha256Hasher>(&[1u8; 20], 100, None, &[token_a], None)
+            .unwrap();
+        assert!(!core::verify_merkle_proof::<Sha256Hasher>(
+            &leaf,
+            &tree.get_proof(0),
+            0,
+            tree.leaf_count(),
+            &tree.root()
+        ));
+    }
+
+    #[test]
+    fn test_merkle_proof_with_vesting() {
+        use core::{NullifierScheme, VestingSchedule};
+
+        let schedule = VestingSchedule {
+            start: 1_700_000_000,
+            cliff: 30 * 86_400,
+            duration: 365 * 86_400,
+        };
+        let records = vec![
+            AllocationRecord {
+                address: [1u8; 20],
+                amount: 1_000,
+                vesting: Some(schedule),
+                basket: Vec::new(),
+                commitment: None,
+            },
+            AllocationRecord {
+                address: [2u8; 20],
+                amount: 1_000,
+                vesting: None,
+                basket: Vec::new(),
+                commitment: None,
+            },
+        ];
+        let tree = MerkleTree::<Sha256Hasher>::from_records(&records);
+        let proof = tree.get_proof(0);
+
+        let leaf = NullifierScheme::AddressV1
+            .compute_leaf::<Sha256Hasher>(&[1u8; 20], 1_000, Some(&schedule), &[], None)
+            .unwrap();
+        assert!(core::verify_merkle_proof::<Sha256Hasher>(
+            &leaf,
+            &proof,
+            0,
+            2,
+            &tree.root()
+        ));
+
+        // The schedule cannot be dropped or shortened
+        let unlocked = NullifierScheme::AddressV1
+            .compute_leaf::<Sha256Hasher>(&[1u8; 20], 1_000, None, &[], None)
+            .unwrap();
+        let shortened = VestingSchedule {
+            duration: 1,
+            ..schedule
+        };
+        let shortened = NullifierScheme::AddressV1
+            .compute_leaf::<Sha256Hasher>(&[1u8; 20], 1_000, Some(&shortened), &[], None)
+            .unwrap();
+        for leaf in [unlocked, shortened] {
+            assert!(!core::verify_merkle_proof::<Sha256Hasher>(
+                &leaf,
+                &proof,
+                0,
+                2,
+                &tree.root()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_with_balances() {
+        use core::NullifierScheme;
+
+        let balances = vec![([1u8; 20], 50), ([2u8; 20], 5_000), ([3u8; 20], 120)];
+        let tree = MerkleTree::<Sha256Hasher>::with_balances(&balances);
+        assert_ne!(
+            tree.root(),
+            MerkleTree::<Sha256Hasher>::new(&balances).root()
+        );
+
+        for (i, (address, balance)) in balances.iter().enumerate() {
+            let leaf = NullifierScheme::AddressV1
+                .compute_balance_leaf::<Sha256Hasher>(address, *balance, None)
+                .unwrap();
+            assert!(core::verify_merkle_proof::<Sha256Hasher>(
+                &leaf,
+                &tree.get_proof(i),
+                i as u32,
+                tree.leaf_count(),
+                &tree.root()
+            ));
         }
     }
+
+    #[test]
+    fn test_intersection_of_two_lists() {
+        use core::ListPolicy;
+
+        let voter = [1u8; 20];
+        let voters = MerkleTree::<Sha256Hasher>::new(&[([9u8; 20], 1), (voter, 1), ([8u8; 20], 1)]);
+        let holders = MerkleTree::<Sha256Hasher>::new(&[(voter, 3), ([7u8; 20], 1)]);
+        let proofs = [voters.list_proof(0, 1, 1), holders.list_proof(1, 0, 3)];
+
+        let policy = ListPolicy::all(vec![voters.root(), holders.root()]);
+        let verify = |address: [u8; 20]| {
+            policy.is_satisfied_by(&proofs, |proof, root| {
+                proof.verify::<Sha256Hasher>(&address, root)
+            })
+        };
+        assert!(verify(voter));
+        assert!(!verify([9u8; 20]));
+    }
 }
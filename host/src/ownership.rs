@@ -0,0 +1,73 @@
+use core::ownership::{
+    claim_message, private_key_address, recover_signer, sign_personal_message, ClaimTerms,
+};
+
+/// Source of the ownership signature for a claim
+pub enum ClaimSigner {
+    /// Sign locally with the eligible address's private key
+    PrivateKey([u8; 32]),
+    /// 65-byte signature produced elsewhere, e.g. by a wallet's `personal_sign`
+    Signature(Vec<u8>),
+}
+
+impl ClaimSigner {
+    /// Produce the ownership signature for `ClaimInput::ownership_signature`
+    ///
+    /// Returns `None` if the signature does not recover to `address`, so a bad
+    /// key or signature is caught before spending time on a proof.
+    pub fn sign_claim(&self, address: &[u8; 20], terms: &ClaimTerms) -> Option<Vec<u8>> {
+        let message = claim_message(terms);
+        let signature = match self {
+            ClaimSigner::PrivateKey(key) => sign_personal_message(key, &message)?,
+            ClaimSigner::Signature(signature) => signature.clone(),
+        };
+
+        if recover_signer(&message, &signature)? != *address {
+            return None;
+        }
+        Some(signature)
+    }
+
+    /// Address controlled by the private key, if signing locally
+    pub fn address(&self) -> Option<[u8; 20]> {
+        match self {
+            ClaimSigner::PrivateKey(key) => private_key_address(key),
+            ClaimSigner::Signature(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_claim_with_key_and_presigned() {
+        let key = [0x22u8; 32];
+        let signer = ClaimSigner::PrivateKey(key);
+        let address = signer.address().unwrap();
+
+        let terms = ClaimTerms {
+            campaign_id: [1u8; 32],
+            epoch_id: 1,
+            chain_id: 1,
+            verifying_contract: [0xc0u8; 20],
+            recipient: [0xaau8; 20],
+            relayer: [0xccu8; 20],
+            fee: 5,
+        };
+        let signature = signer.sign_claim(&address, &terms).unwrap();
+
+        // A pre-made signature is accepted for the same claim only
+        let presigned = ClaimSigner::Signature(signature);
+        assert!(presigned.sign_claim(&address, &terms).is_some());
+        let other_epoch = ClaimTerms {
+            epoch_id: 2,
+            ..terms
+        };
+        assert!(presigned.sign_claim(&address, &other_epoch).is_none());
+        let higher_fee = ClaimTerms { fee: 6, ..terms };
+        assert!(presigned.sign_claim(&address, &higher_fee).is_none());
+        assert!(presigned.sign_claim(&[0u8; 20], &terms).is_none());
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+use crate::journal::{self, JournalError};
+use core::{ClaimInput, ClaimOutput, PublicInputs};
+use methods::{GUEST_CODE_FOR_ZK_PROOF_ELF, GUEST_CODE_FOR_ZK_PROOF_ID};
+use risc0_zkvm::{default_prover, ExecutorEnv, Receipt};
+use serde::{Deserialize, Serialize};
+
+/// Errors from building or accepting a relayer package
+#[derive(Debug)]
+pub enum RelayError {
+    /// The fee is larger than the allocation it is paid from
+    FeeExceedsPayout,
+    /// The claim commits another relayer
+    WrongRelayer,
+    /// The committed fee is below what the relayer asks for
+    FeeTooLow,
+    /// Proving or verifying the receipt failed
+    Prover(String),
+    /// The journal is not a packed `ClaimOutput`
+    InvalidJournal,
+    /// The package is not valid JSON
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayError::FeeExceedsPayout => write!(f, "relayer fee exceeds the payout"),
+            RelayError::WrongRelayer => write!(f, "claim is committed to another relayer"),
+            RelayError::FeeTooLow => write!(f, "relayer fee is too low"),
+            RelayError::Prover(err) => write!(f, "proving failed: {}", err),
+            RelayError::InvalidJournal => write!(f, "journal is not a claim output"),
+            RelayError::Json(err) => write!(f, "invalid relayer package: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RelayError {}
+
+impl From<serde_json::Error> for RelayError {
+    fn from(err: serde_json::Error) -> Self {
+        RelayError::Json(err)
+    }
+}
+
+/// A proven claim a third party can submit with `ZKAirdrop.claim`
+///
+/// The relayer and fee are committed in the journal, so whoever submits the
+/// claim cannot raise its cut or redirect the rest of the payout.
+#[derive(Serialize, Deserialize)]
+pub struct RelayerPackage {
+    /// Receipt of the claim guest; the on-chain seal is derived from it
+    pub receipt: Receipt,
+    /// Decoded journal of `receipt`
+    pub output: ClaimOutput,
+}
+
+impl RelayerPackage {
+    /// Verify a claim receipt and decode its journal
+    pub fn from_receipt(receipt: Receipt) -> Result<Self, RelayError> {
+        receipt
+            .verify(GUEST_CODE_FOR_ZK_PROOF_ID)
+            .map_err(|err| RelayError::Prover(err.to_string()))?;
+        let output = ClaimOutput::from_abi_bytes(&receipt.journal.bytes)
+            .ok_or(RelayError::InvalidJournal)?;
+        Ok(RelayerPackage { receipt, output })
+    }
+
+    /// Packed journal, the `claimOutput` argument of `ZKAirdrop.claim`
+    pub fn journal(&self) -> &[u8] {
+        &self.receipt.journal.bytes
+    }
+
+    /// Check that `relayer` is paid at least `min_fee` for submitting the claim
+    pub fn check_terms(&self, relayer: &[u8; 20], min_fee: u128) -> Result<(), RelayError> {
+        check_terms(&self.output, relayer, min_fee)
+    }
+
+    /// Check that the claim can still be submitted at `now` (Unix seconds)
+    pub fn check_window(&self, now: u64) -> Result<(), JournalError> {
+        journal::check_window(self.output.not_before, self.output.expires_at, now)
+    }
+
+    /// Serialize the package to hand it to a relayer
+    pub fn to_json(&self) -> Result<String, RelayError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Load a package and verify its receipt again; the JSON is untrusted
+    pub fn from_json(json: &str) -> Result<Self, RelayError> {
+        let package: RelayerPackage = serde_json::from_str(json)?;
+        Self::from_receipt(package.receipt)
+    }
+}
+
+/// Prove a claim paying `public_inputs.fee` to `public_inputs.relayer` and
+/// package it for submission
+pub fn prove_relayed_claim(
+    claim_input: &ClaimInput,
+    public_inputs: &PublicInputs,
+) -> Result<RelayerPackage, RelayError> {
+    // Threshold payouts are fixed on-chain, where the fee is bounded instead
+    let threshold = public_inputs.min_balance.is_some();
+    if !threshold && public_inputs.fee > payout(claim_input, public_inputs) {
+        return Err(RelayError::FeeExceedsPayout);
+    }
+
+    let env = ExecutorEnv::builder()
+        .write(claim_input)
+        .and_then(|builder| builder.write(public_inputs))
+        .and_then(|builder| builder.build())
+        .map_err(|err| RelayError::Prover(err.to_string()))?;
+    let receipt = default_prover()
+        .prove(env, GUEST_CODE_FOR_ZK_PROOF_ELF)
+        .map_err(|err| RelayError::Prover(err.to_string()))?
+        .receipt;
+    RelayerPackage::from_receipt(receipt)
+}
+
+/// Amount the claim guest pays out of: the allocation, or for a vesting
+/// allocation what vested since the last tranche (0 if nothing did)
+fn payout(claim_input: &ClaimInput, public_inputs: &PublicInputs) -> u128 {
+    match &claim_input.vesting {
+        None => claim_input.amount,
+        Some(schedule) => schedule
+            .tranche_amount(
+                claim_input.amount,
+                public_inputs.timestamp,
+                public_inputs.claimed_amount,
+            )
+            .unwrap_or(0),
+    }
+}
+
+/// Check that `output` pays `relayer` at least `min_fee`; a zero committed
+/// relayer pays whoever submits
+pub fn check_terms(
+    output: &ClaimOutput,
+    relayer: &[u8; 20],
+    min_fee: u128,
+) -> Result<(), RelayError> {
+    if output.relayer != [0u8; 20] && output.relayer != *relayer {
+        return Err(RelayError::WrongRelayer);
+    }
+    if output.fee < min_fee {
+        return Err(RelayError::FeeTooLow);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::allocation::EMPTY_BASKET_DIGEST;
+    use core::HashScheme;
+
+    #[test]
+    fn test_check_terms() {
+        let output = ClaimOutput {
+            merkle_root: [1u8; 32],
+            nullifier: [2u8; 32],
+            epoch_id: 1,
+            recipient: [3u8; 20],
+            campaign_id: [4u8; 32],
+            ownership_verified: true,
+            hash_scheme: HashScheme::Sha256,
+            amount: 1_000,
+            basket_digest: EMPTY_BASKET_DIGEST,
+            timestamp: 0,
+            claimed_amount: 0,
+            next_nullifier: [0u8; 32],
+            min_balance: 0,
+            exclusion_root: [0u8; 32],
+            policy_commitment: [0u8; 32],
+            relayer: [5u8; 20],
+            fee: 10,
+            chain_id: 1,
+            verifying_contract: [6u8; 20],
+            not_before: 0,
+            expires_at: 0,
+            basket: Vec::new(),
+        };
+        assert!(check_terms(&output, &[5u8; 20], 10).is_ok());
+        assert!(matches!(
+            check_terms(&output, &[6u8; 20], 10),
+            Err(RelayError::WrongRelayer)
+        ));
+        assert!(matches!(
+            check_terms(&output, &[5u8; 20], 11),
+            Err(RelayError::FeeTooLow)
+        ));
+
+        // Without a committed relayer any submitter is paid
+        let open = ClaimOutput {
+            relayer: [0u8; 20],
+            ..output
+        };
+        assert!(check_terms(&open, &[6u8; 20], 10).is_ok());
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use core::sparse::{empty_subtrees, key_bit, sparse_key, sparse_leaf, SPARSE_DEPTH};
+use core::{MerkleHasher, Sha256Hasher, SparseMerkleProof};
+
+/// A sparse Merkle tree of excluded addresses, hashed with `H`
+pub struct SparseMerkleTree<H: MerkleHasher = Sha256Hasher> {
+    /// Non-empty nodes keyed by `(level, key prefix)`; level 0 holds the leaves
+    nodes: HashMap<(usize, [u8; 32]), [u8; 32]>,
+    /// Empty subtree roots per level
+    empty: Vec<[u8; 32]>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> SparseMerkleTree<H> {
+    /// Build an empty tree
+    pub fn new() -> Self {
+        SparseMerkleTree {
+            nodes: HashMap::new(),
+            empty: empty_subtrees::<H>(),
+            _hasher: PhantomData,
+        }
+    }
+
+    /// Build a tree holding `addresses`
+    pub fn from_addresses(addresses: &[[u8; 20]]) -> Self {
+        let mut tree = Self::new();
+        for address in addresses {
+            tree.insert(&sparse_key(address));
+        }
+        tree
+    }
+
+    /// Insert `key` and rehash its path
+    pub fn insert(&mut self, key: &[u8; 32]) {
+        let mut computed_hash = sparse_leaf::<H>(key);
+        self.nodes.insert((0, *key), computed_hash);
+
+        for level in 0..SPARSE_DEPTH {
+            let sibling = self.node(level, &sibling_prefix(key, level));
+            computed_hash = if key_bit(key, level) {
+                H::hash_node(&sibling, &computed_hash)
+            } else {
+                H::hash_node(&computed_hash, &sibling)
+            };
+            self.nodes
+                .insert((level + 1, prefix(key, level + 1)), computed_hash);
+        }
+    }
+
+    /// Insert the key of `address`
+    pub fn insert_address(&mut self, address: &[u8; 20]) {
+        self.insert(&sparse_key(address));
+    }
+
+    /// Whether `key` is in the tree
+    pub fn contains(&self, key: &[u8; 32]) -> bool {
+        self.nodes.contains_key(&(0, *key))
+    }
+
+    /// Path from the slot of `key` to the root; proves membership if `key`
+    /// is in the tree and non-membership otherwise
+    pub fn get_proof(&self, key: &[u8; 32]) -> SparseMerkleProof {
+        let mut proof = SparseMerkleProof::default();
+        for level in 0..SPARSE_DEPTH {
+            if let Some(sibling) = self.nodes.get(&(level, sibling_prefix(key, level))) {
+                proof.set_sibling(level);
+                proof.siblings.push(*sibling);
+            }
+        }
+        proof
+    }
+
+    /// Get the root hash
+    pub fn root(&self) -> [u8; 32] {
+        self.node(SPARSE_DEPTH, &[0u8; 32])
+    }
+
+    /// Hash of the node at `level` covering `prefix`
+    fn node(&self, level: usize, prefix: &[u8; 32]) -> [u8; 32] {
+        self.nodes
+            .get(&(level, *prefix))
+            .copied()
+            .unwrap_or(self.empty[level])
+    }
+}
+
+impl<H: MerkleHasher> Default for SparseMerkleTree<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `key` with the `level` low bits cleared: the node at `level` above it
+fn prefix(key: &[u8; 32], level: usize) -> [u8; 32] {
+    let mut prefix = *key;
+    let full_bytes = level / 8;
+    prefix[32 - full_bytes..].fill(0);
+    if !level.is_multiple_of(8) {
+        prefix[31 - full_bytes] &= 0xff << (level % 8);
+    }
+    prefix
+}
+
+/// Prefix of the sibling of the node at `level` above `key`
+fn sibling_prefix(key: &[u8; 32], level: usize) -> [u8; 32] {
+    let mut sibling = prefix(key, level);
+    sibling[31 - level / 8] ^= 1 << (level % 8);
+    sibling
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sparse::{verify_sparse_membership, verify_sparse_non_membership};
+    use core::Keccak256Hasher as H;
+
+    #[test]
+    fn test_membership_and_non_membership() {
+        let excluded = [[1u8; 20], [2u8; 20], [3u8; 20]];
+        let tree = SparseMerkleTree::<H>::from_addresses(&excluded);
+        let root = tree.root();
+
+        for address in &excluded {
+            let key = sparse_key(address);
+            let proof = tree.get_proof(&key);
+            assert!(tree.contains(&key));
+            assert!(verify_sparse_membership::<H>(&key, &proof, &root));
+            assert!(!verify_sparse_non_membership::<H>(&key, &proof, &root));
+        }
+
+        let key = sparse_key(&[4u8; 20]);
+        let proof = tree.get_proof(&key);
+        assert!(!tree.contains(&key));
+        assert!(verify_sparse_non_membership::<H>(&key, &proof, &root));
+        assert!(!verify_sparse_membership::<H>(&key, &proof, &root));
+    }
+
+    #[test]
+    fn test_root_is_order_independent() {
+        let mut forward = SparseMerkleTree::<H>::new();
+        let mut backward = SparseMerkleTree::<H>::new();
+        assert_eq!(forward.root(), empty_subtrees::<H>()[SPARSE_DEPTH]);
+
+        for i in 0..8u8 {
+            forward.insert_address(&[i; 20]);
+            backward.insert_address(&[7 - i; 20]);
+        }
+        assert_eq!(forward.root(), backward.root());
+
+        // Adjacent keys share every sibling but the leaf level
+        let mut a = [0u8; 32];
+        let mut b = [0u8; 32];
+        b[31] = 1;
+        let mut tree = SparseMerkleTree::<H>::new();
+        tree.insert(&a);
+        tree.insert(&b);
+        let root = tree.root();
+        let proof = tree.get_proof(&a);
+        assert_eq!(proof.siblings.len(), 1);
+        assert!(verify_sparse_membership::<H>(&a, &proof, &root));
+
+        a[0] = 0x80;
+        assert!(verify_sparse_non_membership::<H>(
+            &a,
+            &tree.get_proof(&a),
+            &root
+        ));
+    }
+}
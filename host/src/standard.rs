@@ -0,0 +1,468 @@
+use std::fmt;
+
+use core::standard::{abi_encode_address, balance_tag_word, hash_sorted_pair, standard_leaf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Format tag written by `@openzeppelin/merkle-tree`
+const STANDARD_FORMAT: &str = "standard-v1";
+
+/// Errors from loading or building a `StandardMerkleTree`
+#[derive(Debug)]
+pub enum StandardTreeError {
+    /// The dump is not valid JSON or has the wrong shape
+    Json(serde_json::Error),
+    /// There are no values to build a tree from
+    Empty,
+    /// The `format` field is not `standard-v1`
+    UnsupportedFormat(String),
+    /// A leaf encoding type other than `address`, `uint256`, `uint64` or `bytes32`
+    UnsupportedType(String),
+    /// A value does not parse as its declared type
+    InvalidValue(String),
+    /// Tree hashes or tree indices are inconsistent with the values
+    InvalidTree,
+}
+
+impl fmt::Display for StandardTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StandardTreeError::Json(err) => write!(f, "invalid tree dump: {}", err),
+            StandardTreeError::Empty => write!(f, "cannot build tree from empty list"),
+            StandardTreeError::UnsupportedFormat(format) => {
+                write!(f, "unsupported tree format: {}", format)
+            }
+            StandardTreeError::UnsupportedType(ty) => write!(f, "unsupported leaf type: {}", ty),
+            StandardTreeError::InvalidValue(value) => write!(f, "invalid leaf value: {}", value),
+            StandardTreeError::InvalidTree => write!(f, "tree does not match its values"),
+        }
+    }
+}
+
+impl std::error::Error for StandardTreeError {}
+
+impl From<serde_json::Error> for StandardTreeError {
+    fn from(err: serde_json::Error) -> Self {
+        StandardTreeError::Json(err)
+    }
+}
+
+/// A leaf value and its position in the flattened tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StandardValue {
+    /// Values as written in the dump (hex addresses, decimal or hex integers)
+    pub value: Vec<String>,
+    /// Index of the leaf in `StandardMerkleTree::tree`
+    pub tree_index: usize,
+}
+
+/// An OpenZeppelin `StandardMerkleTree`
+///
+/// The tree is stored flattened with the root at index 0 and the children of
+/// node `i` at `2i + 1` and `2i + 2`, exactly as in the JSON dump.
+#[derive(Clone, Debug)]
+pub struct StandardMerkleTree {
+    /// Solidity types of each value column
+    pub leaf_encoding: Vec<String>,
+    /// Flattened tree nodes
+    pub tree: Vec<[u8; 32]>,
+    /// Leaf values in insertion order
+    pub values: Vec<StandardValue>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TreeDump {
+    format: String,
+    #[serde(rename = "leafEncoding")]
+    leaf_encoding: Vec<String>,
+    tree: Vec<String>,
+    values: Vec<ValueDump>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ValueDump {
+    value: Vec<Value>,
+    #[serde(rename = "treeIndex")]
+    tree_index: usize,
+}
+
+impl StandardMerkleTree {
+    /// Build a tree the way `StandardMerkleTree.of` does, sorting leaves by hash
+    pub fn of(values: Vec<Vec<String>>, leaf_encoding: &[&str]) -> Result<Self, StandardTreeError> {
+        if values.is_empty() {
+            return Err(StandardTreeError::Empty);
+        }
+
+        let leaf_encoding: Vec<String> = leaf_encoding.iter().map(|ty| ty.to_string()).collect();
+        let mut leaves = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Ok((leaf_hash(&leaf_encoding, value)?, i)))
+            .collect::<Result<Vec<_>, StandardTreeError>>()?;
+        leaves.sort();
+
+        let mut tree = vec![[0u8; 32]; 2 * leaves.len() - 1];
+        let mut tree_indices = vec![0; leaves.len()];
+        for (i, (hash, value_index)) in leaves.iter().enumerate() {
+            let tree_index = tree.len() - 1 - i;
+            tree[tree_index] = *hash;
+            tree_indices[*value_index] = tree_index;
+        }
+        for i in (0..tree.len() - leaves.len()).rev() {
+            tree[i] = hash_sorted_pair(&tree[2 * i + 1], &tree[2 * i + 2]);
+        }
+
+        let values = values
+            .into_iter()
+            .zip(tree_indices)
+            .map(|(value, tree_index)| StandardValue { value, tree_index })
+            .collect();
+
+        Ok(StandardMerkleTree {
+            leaf_encoding,
+            tree,
+            values,
+        })
+    }
+
+    /// Build an `["address", "uint256"]` tree from `(address, amount)` allocations
+    pub fn from_allocations(allocations: &[([u8; 20], u128)]) -> Self {
+        assert!(!allocations.is_empty(), "Cannot build tree from empty list");
+
+        let values = allocations
+            .iter()
+            .map(|(address, amount)| {
+                vec![format!("0x{}", hex::encode(address)), amount.to_string()]
+            })
+            .collect();
+        Self::of(values, &["address", "uint256"]).expect("Allocations are always valid")
+    }
+
+    /// Build a balance snapshot tree from `(address, balance)` pairs for threshold
+    /// and tier campaigns; leaves are `["bytes32", "address", "uint256"]` led by
+    /// `balance_tag_word`, so they never verify as allocations
+    pub fn from_balances(balances: &[([u8; 20], u128)]) -> Self {
+        assert!(!balances.is_empty(), "Cannot build tree from empty list");
+
+        let tag = format!("0x{}", hex::encode(balance_tag_word()));
+        let values = balances
+            .iter()
+            .map(|(address, balance)| {
+                vec![
+                    tag.clone(),
+                    format!("0x{}", hex::encode(address)),
+                    balance.to_string(),
+                ]
+            })
+            .collect();
+        Self::of(values, &["bytes32", "address", "uint256"]).expect("Balances are always valid")
+    }
+
+    /// Load and validate a JSON dump
+    pub fn load(json: &str) -> Result<Self, StandardTreeError> {
+        let dump: TreeDump = serde_json::from_str(json)?;
+        if dump.format != STANDARD_FORMAT {
+            return Err(StandardTreeError::UnsupportedFormat(dump.format));
+        }
+
+        let tree = dump
+            .tree
+            .iter()
+            .map(|node| {
+                parse_bytes32(node).ok_or_else(|| StandardTreeError::InvalidValue(node.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = dump
+            .values
+            .into_iter()
+            .map(|entry| {
+                let value = entry
+                    .value
+                    .iter()
+                    .map(value_to_string)
+                    .collect::<Result<_, _>>()?;
+                Ok(StandardValue {
+                    value,
+                    tree_index: entry.tree_index,
+                })
+            })
+            .collect::<Result<Vec<_>, StandardTreeError>>()?;
+
+        let tree = StandardMerkleTree {
+            leaf_encoding: dump.leaf_encoding,
+            tree,
+            values,
+        };
+        tree.validate()?;
+        Ok(tree)
+    }
+
+    /// Export as a JSON dump readable by `StandardMerkleTree.load`
+    pub fn dump(&self) -> String {
+        let dump = TreeDump {
+            format: STANDARD_FORMAT.to_string(),
+            leaf_encoding: self.leaf_encoding.clone(),
+            tree: self
+                .tree
+                .iter()
+                .map(|node| format!("0x{}", hex::encode(node)))
+                .collect(),
+            values: self
+                .values
+                .iter()
+                .map(|entry| ValueDump {
+                    value: entry.value.iter().cloned().map(Value::String).collect(),
+                    tree_index: entry.tree_index,
+                })
+                .collect(),
+        };
+        serde_json::to_string_pretty(&dump).expect("Tree dump is always serializable")
+    }
+
+    /// Check that every leaf and internal node matches the values
+    pub fn validate(&self) -> Result<(), StandardTreeError> {
+        let leaf_count = self.values.len();
+        if leaf_count == 0 || self.tree.len() != 2 * leaf_count - 1 {
+            return Err(StandardTreeError::InvalidTree);
+        }
+
+        let first_leaf = self.tree.len() - leaf_count;
+        for entry in &self.values {
+            if entry.tree_index < first_leaf || entry.tree_index >= self.tree.len() {
+                return Err(StandardTreeError::InvalidTree);
+            }
+            if self.tree[entry.tree_index] != leaf_hash(&self.leaf_encoding, &entry.value)? {
+                return Err(StandardTreeError::InvalidTree);
+            }
+        }
+        for i in 0..first_leaf {
+            if self.tree[i] != hash_sorted_pair(&self.tree[2 * i + 1], &self.tree[2 * i + 2]) {
+                return Err(StandardTreeError::InvalidTree);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the root hash
+    pub fn root(&self) -> [u8; 32] {
+        self.tree[0]
+    }
+
+    /// Leaf hash of the value at `value_index`
+    pub fn leaf(&self, value_index: usize) -> [u8; 32] {
+        self.tree[self.values[value_index].tree_index]
+    }
+
+    /// Get the sorted-pair proof for the value at `value_index`
+    pub fn get_proof(&self, value_index: usize) -> Vec<[u8; 32]> {
+        assert!(value_index < self.values.len(), "Index out of bounds");
+
+        let mut proof = Vec::new();
+        let mut index = self.values[value_index].tree_index;
+        while index > 0 {
+            let sibling = if index % 2 == 1 { index + 1 } else { index - 1 };
+            proof.push(self.tree[sibling]);
+            index = (index - 1) / 2;
+        }
+
+        proof
+    }
+}
+
+/// Hash a leaf value according to its declared encoding
+fn leaf_hash(leaf_encoding: &[String], value: &[String]) -> Result<[u8; 32], StandardTreeError> {
+    if leaf_encoding.len() != value.len() {
+        return Err(StandardTreeError::InvalidValue(value.join(",")));
+    }
+
+    let words = leaf_encoding
+        .iter()
+        .zip(value)
+        .map(|(ty, value)| abi_encode(ty, value))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(standard_leaf(&words))
+}
+
+/// ABI-encode one static value as a 32-byte word
+fn abi_encode(ty: &str, value: &str) -> Result<[u8; 32], StandardTreeError> {
+    let word = match ty {
+        "address" => parse_address(value).map(|address| abi_encode_address(&address)),
+        "uint256" => parse_uint256(value),
+        "uint64" => parse_uint256(value).filter(|word| word[..24].iter().all(|&byte| byte == 0)),
+        "bytes32" => parse_bytes32(value),
+        _ => return Err(StandardTreeError::UnsupportedType(ty.to_string())),
+    };
+    word.ok_or_else(|| StandardTreeError::InvalidValue(value.to_string()))
+}
+
+fn value_to_string(value: &Value) -> Result<String, StandardTreeError> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(number) if number.is_u64() => Ok(number.to_string()),
+        other => Err(StandardTreeError::InvalidValue(other.to_string())),
+    }
+}
+
+pub(crate) fn parse_address(value: &str) -> Option<[u8; 20]> {
+    let bytes = hex::decode(value.strip_prefix("0x")?).ok()?;
+    bytes.try_into().ok()
+}
+
+fn parse_bytes32(value: &str) -> Option<[u8; 32]> {
+    let bytes = hex::decode(value.strip_prefix("0x")?).ok()?;
+    bytes.try_into().ok()
+}
+
+/// Parse a decimal or `0x`-prefixed hex integer into a big-endian word
+fn parse_uint256(value: &str) -> Option<[u8; 32]> {
+    let mut word = [0u8; 32];
+
+    if let Some(digits) = value.strip_prefix("0x") {
+        if digits.is_empty() || digits.len() > 64 {
+            return None;
+        }
+        let padded = format!("{:0>64}", digits);
+        hex::decode_to_slice(padded, &mut word).ok()?;
+        return Some(word);
+    }
+
+    if value.is_empty() {
+        return None;
+    }
+    for digit in value.chars() {
+        let mut carry = digit.to_digit(10)?;
+        for byte in word.iter_mut().rev() {
+            let product = *byte as u32 * 10 + carry;
+            *byte = product as u8;
+            carry = product >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::standard::verify_sorted_merkle_proof;
+
+    // Dump from the @openzeppelin/merkle-tree README
+    const README_DUMP: &str = r#"{
+        "format": "standard-v1",
+        "tree": [
+            "0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77",
+            "0xeb02c421cfa48976e66dfb29120745909ea3a0f843456c263cf8f1253483e283",
+            "0xb92c48e9d7abe27fd8dfd6b5dfdbfb1c9a463f80c712b66f3a5180a090cccafc"
+        ],
+        "values": [
+            { "value": ["0x1111111111111111111111111111111111111111", "5000000000000000000"], "treeIndex": 1 },
+            { "value": ["0x2222222222222222222222222222222222222222", "2500000000000000000"], "treeIndex": 2 }
+        ],
+        "leafEncoding": ["address", "uint256"]
+    }"#;
+
+    #[test]
+    fn test_load_openzeppelin_dump() {
+        let tree = StandardMerkleTree::load(README_DUMP).unwrap();
+        for i in 0..tree.values.len() {
+            assert!(verify_sorted_merkle_proof(
+                &tree.leaf(i),
+                &tree.get_proof(i),
+                &tree.root()
+            ));
+        }
+
+        // Building from the same values reproduces the dump
+        let rebuilt = StandardMerkleTree::of(
+            tree.values
+                .iter()
+                .map(|entry| entry.value.clone())
+                .collect(),
+            &["address", "uint256"],
+        )
+        .unwrap();
+        assert_eq!(rebuilt.tree, tree.tree);
+        assert_eq!(rebuilt.values, tree.values);
+    }
+
+    #[test]
+    fn test_dump_roundtrip() {
+        let allocations: Vec<([u8; 20], u128)> =
+            (1..=5u8).map(|i| ([i; 20], i as u128 * 100)).collect();
+        let tree = StandardMerkleTree::from_allocations(&allocations);
+        let loaded = StandardMerkleTree::load(&tree.dump()).unwrap();
+        assert_eq!(loaded.tree, tree.tree);
+        assert_eq!(loaded.values, tree.values);
+
+        for (i, (address, amount)) in allocations.iter().enumerate() {
+            let leaf = core::NullifierScheme::AddressV1
+                .compute_standard_leaf(address, *amount, None, &[], None)
+                .unwrap();
+            assert_eq!(leaf, tree.leaf(i));
+            assert!(verify_sorted_merkle_proof(
+                &leaf,
+                &tree.get_proof(i),
+                &tree.root()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_balance_leaves_are_not_allocations() {
+        let balances = [([1u8; 20], 500), ([2u8; 20], 700)];
+        let tree = StandardMerkleTree::from_balances(&balances);
+        let scheme = core::NullifierScheme::AddressV1;
+        for (i, (address, balance)) in balances.iter().enumerate() {
+            let leaf = scheme
+                .compute_standard_balance_leaf(address, *balance, None)
+                .unwrap();
+            assert_eq!(leaf, tree.leaf(i));
+            assert!(verify_sorted_merkle_proof(
+                &leaf,
+                &tree.get_proof(i),
+                &tree.root()
+            ));
+        }
+        assert_ne!(
+            StandardMerkleTree::from_allocations(&balances).root(),
+            tree.root()
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_tampered_dump() {
+        let tampered = README_DUMP.replace("5000000000000000000", "5000000000000000001");
+        assert!(matches!(
+            StandardMerkleTree::load(&tampered),
+            Err(StandardTreeError::InvalidTree)
+        ));
+
+        let unsupported = README_DUMP.replace("\"uint256\"", "\"string\"");
+        assert!(matches!(
+            StandardMerkleTree::load(&unsupported),
+            Err(StandardTreeError::UnsupportedType(_))
+        ));
+
+        assert!(matches!(
+            StandardMerkleTree::of(Vec::new(), &["address", "uint256"]),
+            Err(StandardTreeError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_parse_uint256() {
+        let mut expected = [0u8; 32];
+        expected[31] = 0xff;
+        expected[30] = 0x01;
+        assert_eq!(parse_uint256("511"), Some(expected));
+        assert_eq!(parse_uint256("0x1ff"), Some(expected));
+        assert_eq!(parse_uint256(&"9".repeat(78)), None);
+        assert_eq!(parse_uint256("12a"), None);
+
+        assert!(abi_encode("uint64", &u64::MAX.to_string()).is_ok());
+        assert!(abi_encode("uint64", &(u64::MAX as u128 + 1).to_string()).is_err());
+    }
+}
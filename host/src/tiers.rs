@@ -0,0 +1,66 @@
+use core::TierTable;
+
+/// Split a score snapshot into `tiers` tiers of roughly equal population
+///
+/// Lower bounds are taken at the score quantiles, so recipients with equal
+/// scores always share a tier; ties can merge tiers and yield fewer than
+/// requested. Returns `None` for an empty snapshot or zero tiers.
+pub fn quantile_tiers(scores: &[u128], tiers: usize) -> Option<TierTable> {
+    if scores.is_empty() || tiers == 0 {
+        return None;
+    }
+
+    let mut sorted = scores.to_vec();
+    sorted.sort_unstable();
+    let tiers = tiers.min(sorted.len());
+    let mut lower_bounds: Vec<u128> = (0..tiers)
+        .map(|tier| sorted[tier * sorted.len() / tiers])
+        .collect();
+    lower_bounds.dedup();
+    TierTable::new(lower_bounds)
+}
+
+/// Number of scores in each tier of `table`; scores below the first bound are not counted
+pub fn tier_histogram(table: &TierTable, scores: &[u128]) -> Vec<usize> {
+    let mut counts = vec![0; table.lower_bounds.len()];
+    for tier in scores.iter().filter_map(|&score| table.tier_of(score)) {
+        counts[tier as usize] += 1;
+    }
+    counts
+}
+
+/// Total payout if every score in the snapshot claims `rewards[tier]`, or
+/// `None` if `rewards` does not cover every tier or the total overflows
+pub fn tier_budget(table: &TierTable, scores: &[u128], rewards: &[u128]) -> Option<u128> {
+    if rewards.len() != table.lower_bounds.len() {
+        return None;
+    }
+
+    tier_histogram(table, scores)
+        .iter()
+        .zip(rewards)
+        .try_fold(0u128, |total, (&count, &reward)| {
+            total.checked_add(reward.checked_mul(count as u128)?)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantile_tiers() {
+        let scores = [5, 1, 9, 3, 7, 2, 8, 4, 6, 10];
+        let table = quantile_tiers(&scores, 3).unwrap();
+        assert_eq!(table.lower_bounds, vec![1, 4, 7]);
+        assert_eq!(tier_histogram(&table, &scores), vec![3, 3, 4]);
+        assert_eq!(tier_budget(&table, &scores, &[1, 10, 100]), Some(433));
+        assert_eq!(tier_budget(&table, &scores, &[1, 10]), None);
+
+        // Ties collapse into one tier
+        let table = quantile_tiers(&[4, 4, 4, 9], 4).unwrap();
+        assert_eq!(table.lower_bounds, vec![4, 9]);
+
+        assert!(quantile_tiers(&[], 3).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::io;
+
+use core::MerkleHasher;
+use sha2::{Digest, Sha256};
+
+/// Magic bytes at the start of a tree file
+pub(crate) const MAGIC: [u8; 8] = *b"ZKMTREE\0";
+/// Version of the tree file layout
+pub(crate) const VERSION: u16 = 1;
+/// Length of the tree file header; the levels follow it, leaves first, then
+/// the address index
+pub(crate) const HEADER_LEN: usize = 64;
+/// Length of the SHA-256 checksum over the header, levels and index that ends the file
+pub(crate) const CHECKSUM_LEN: usize = 32;
+
+/// Errors from saving or loading a tree file
+#[derive(Debug)]
+pub enum TreeFileError {
+    /// Reading or writing the file failed
+    Io(io::Error),
+    /// The file does not start with a tree file header
+    InvalidHeader,
+    /// The file was written in another version of the layout
+    UnsupportedVersion(u16),
+    /// The tree was built with another hash scheme
+    WrongScheme,
+    /// The leaf count or depth does not match the file length
+    InvalidLength,
+    /// The checksum does not match the contents
+    ChecksumMismatch,
+    /// The levels do not hash up to the root in the header
+    RootMismatch,
+    /// The address index does not match the leaves
+    InvalidIndex,
+}
+
+impl fmt::Display for TreeFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeFileError::Io(err) => write!(f, "tree file error: {}", err),
+            TreeFileError::InvalidHeader => write!(f, "not a tree file"),
+            TreeFileError::UnsupportedVersion(version) => {
+                write!(f, "unsupported tree file version: {}", version)
+            }
+            TreeFileError::WrongScheme => write!(f, "tree was built with another hash scheme"),
+            TreeFileError::InvalidLength => write!(f, "tree file has the wrong length"),
+            TreeFileError::ChecksumMismatch => write!(f, "tree file checksum mismatch"),
+            TreeFileError::RootMismatch => write!(f, "tree levels do not match the root"),
+            TreeFileError::InvalidIndex => write!(f, "address index does not match the leaves"),
+        }
+    }
+}
+
+impl std::error::Error for TreeFileError {}
+
+impl From<io::Error> for TreeFileError {
+    fn from(err: io::Error) -> Self {
+        TreeFileError::Io(err)
+    }
+}
+
+/// Fixed-size header of a tree file
+///
+/// Encoded as magic, version (LE), hash scheme, depth, 4 zero bytes, leaf
+/// count (LE), root and index length in bytes (LE).
+pub(crate) struct Header {
+    pub leaf_count: u64,
+    pub root: [u8; 32],
+    pub index_len: u64,
+}
+
+impl Header {
+    pub fn encode<H: MerkleHasher>(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0u8; HEADER_LEN];
+        header[0..8].copy_from_slice(&MAGIC);
+        header[8..10].copy_from_slice(&VERSION.to_le_bytes());
+        header[10] = H::SCHEME as u8;
+        header[11] = Layout::new(self.leaf_count, 0).depth() as u8;
+        header[16..24].copy_from_slice(&self.leaf_count.to_le_bytes());
+        header[24..56].copy_from_slice(&self.root);
+        header[56..64].copy_from_slice(&self.index_len.to_le_bytes());
+        header
+    }
+
+    /// Decode a header written for hasher `H`
+    pub fn decode<H: MerkleHasher>(bytes: &[u8]) -> Result<Self, TreeFileError> {
+        if bytes.len() < HEADER_LEN || bytes[0..8] != MAGIC {
+            return Err(TreeFileError::InvalidHeader);
+        }
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+        if version != VERSION {
+            return Err(TreeFileError::UnsupportedVersion(version));
+        }
+        if bytes[10] != H::SCHEME as u8 {
+            return Err(TreeFileError::WrongScheme);
+        }
+
+        let mut leaf_count_bytes = [0u8; 8];
+        leaf_count_bytes.copy_from_slice(&bytes[16..24]);
+        let leaf_count = u64::from_le_bytes(leaf_count_bytes);
+        if leaf_count == 0
+            || leaf_count > u32::MAX as u64
+            || bytes[11] as usize != Layout::new(leaf_count, 0).depth()
+        {
+            return Err(TreeFileError::InvalidLength);
+        }
+        let mut root = [0u8; 32];
+        root.copy_from_slice(&bytes[24..56]);
+        let mut index_len_bytes = [0u8; 8];
+        index_len_bytes.copy_from_slice(&bytes[56..64]);
+
+        Ok(Header {
+            leaf_count,
+            root,
+            index_len: u64::from_le_bytes(index_len_bytes),
+        })
+    }
+}
+
+/// Node counts and byte offsets of each level of a tree file, and the
+/// length of the index after them
+pub(crate) struct Layout {
+    pub lens: Vec<usize>,
+    pub offsets: Vec<usize>,
+    pub index_len: usize,
+}
+
+impl Layout {
+    pub fn new(leaf_count: u64, index_len: u64) -> Self {
+        let mut lens = vec![leaf_count as usize];
+        while lens[lens.len() - 1] > 1 {
+            lens.push(lens[lens.len() - 1].div_ceil(2));
+        }
+        let mut offsets = Vec::with_capacity(lens.len());
+        let mut offset = HEADER_LEN;
+        for len in &lens {
+            offsets.push(offset);
+            offset += len * 32;
+        }
+        Layout {
+            lens,
+            offsets,
+            index_len: index_len as usize,
+        }
+    }
+
+    /// Number of levels above the leaves
+    pub fn depth(&self) -> usize {
+        self.lens.len() - 1
+    }
+
+    /// Offset of the address index, right after the top node
+    pub fn index_offset(&self) -> usize {
+        self.offsets[self.depth()] + 32
+    }
+
+    /// Offset of the checksum, right after the index
+    pub fn checksum_offset(&self) -> usize {
+        self.index_offset().saturating_add(self.index_len)
+    }
+
+    pub fn file_len(&self) -> usize {
+        self.checksum_offset().saturating_add(CHECKSUM_LEN)
+    }
+}
+
+/// Checksum of the header, levels and index, given in file order
+pub(crate) fn checksum<'a>(contents: impl IntoIterator<Item = &'a [u8]>) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in contents {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
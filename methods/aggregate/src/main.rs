@@ -0,0 +1,34 @@
+#![no_main]
+
+use core::batch::image_id_bytes;
+use core::{AggregateClaimOutput, BatchClaimOutput, ClaimOutput};
+use risc0_zkvm::guest::env;
+
+risc0_zkvm::guest::entry!(main);
+
+fn main() {
+    // Read the image ID the claim receipts were proven under
+    let claim_image_id: [u32; 8] = env::read();
+
+    // Read the packed `ClaimOutput` journal of each claim receipt; the
+    // receipts themselves are supplied by the host as assumptions
+    let journals: Vec<Vec<u8>> = env::read();
+
+    // Step 1: Verify every claim receipt; the claimers' inputs never reach this guest
+    let mut outputs = Vec::with_capacity(journals.len());
+    for journal in &journals {
+        env::verify(claim_image_id, journal).expect("Invalid claim receipt");
+        outputs.push(ClaimOutput::from_abi_bytes(journal).expect("Not a claim journal"));
+    }
+
+    // Step 2: Fold the claims into one batch; they must share a campaign,
+    // pay plain allocations and carry distinct nullifiers
+    let batch = BatchClaimOutput::from_claims(&outputs).expect("Claims do not fold into one batch");
+
+    // Step 3: Commit the batch under the claim image it was proven with
+    let output = AggregateClaimOutput {
+        claim_image_id: image_id_bytes(&claim_image_id),
+        batch,
+    };
+    env::commit_slice(&output.to_abi_bytes());
+}
@@ -0,0 +1,147 @@
+#![no_main]
+
+use std::collections::BTreeSet;
+
+use core::claim::{verify_exclusion, verify_list_policy, verify_membership, verify_ownership};
+use core::ownership::ClaimTerms;
+use core::{
+    compute_domain_nullifier, root_set_commitment, BatchClaim, BatchClaimOutput, BatchEntry,
+    PublicInputs,
+};
+use risc0_zkvm::guest::env;
+
+risc0_zkvm::guest::entry!(main);
+
+fn main() {
+    // Read private inputs (every claim of the batch)
+    let claims: Vec<BatchClaim> = env::read();
+
+    // Read public inputs shared by the batch; `recipient` is taken per claim
+    let public_inputs: PublicInputs = env::read();
+
+    // Step 1: Batches pay plain allocations; vesting, threshold, tiered, relayed
+    // and time-limited claims each commit fields the batch journal does not carry
+    assert!(!claims.is_empty(), "Empty batch");
+    assert!(
+        public_inputs.min_balance.is_none() && public_inputs.tiers.is_none(),
+        "Batches carry no threshold or tiers"
+    );
+    assert_eq!(public_inputs.claimed_amount, 0, "Allocation does not vest");
+    assert_eq!(public_inputs.fee, 0, "Batches carry no relayer fee");
+    assert!(
+        public_inputs.not_before.is_none() && public_inputs.expires_at.is_none(),
+        "Batches carry no validity window"
+    );
+
+    // Step 2: Check the root set once; each claim picks its root privately
+    if let Some(roots) = &public_inputs.root_set {
+        assert_eq!(
+            root_set_commitment(roots),
+            public_inputs.merkle_root,
+            "Root set commitment mismatch"
+        );
+    }
+
+    let mut nullifiers = BTreeSet::new();
+    let mut entries = Vec::with_capacity(claims.len());
+    for BatchClaim {
+        input: claim_input,
+        recipient,
+    } in &claims
+    {
+        // Step 3: Verify the claim like a single claim
+        assert!(
+            claim_input.vesting.is_none()
+                && claim_input.basket.is_empty()
+                && claim_input.balance.is_none(),
+            "Batch claims pay a plain allocation"
+        );
+        assert_eq!(
+            claim_input.epoch_id, public_inputs.epoch_id,
+            "Epoch ID mismatch"
+        );
+
+        let merkle_root = match &public_inputs.root_set {
+            None => public_inputs.merkle_root,
+            Some(roots) => {
+                let root_index = claim_input.root_index.expect("Missing root index");
+                *roots
+                    .get(root_index as usize)
+                    .expect("Root index out of range")
+            }
+        };
+        assert!(
+            verify_membership(claim_input, public_inputs.hash_scheme, &merkle_root),
+            "Invalid Merkle proof"
+        );
+
+        if let Some(exclusion_root) = public_inputs.exclusion_root.as_ref() {
+            let proof = claim_input
+                .exclusion_proof
+                .as_ref()
+                .expect("Missing exclusion proof");
+            let not_excluded = verify_exclusion(
+                &claim_input.user_address,
+                proof,
+                public_inputs.hash_scheme,
+                exclusion_root,
+            );
+            assert!(not_excluded, "Address is excluded");
+        }
+
+        if let Some(policy) = public_inputs.list_policy.as_ref() {
+            let is_satisfied = verify_list_policy(claim_input, public_inputs.hash_scheme, policy);
+            assert!(is_satisfied, "List policy not satisfied");
+        }
+
+        if public_inputs.require_ownership {
+            let terms = ClaimTerms::new(&public_inputs, recipient);
+            assert!(
+                verify_ownership(claim_input, &terms),
+                "Invalid ownership signature"
+            );
+        }
+
+        // Step 4: Compute the deployment-bound nullifier; the contract only checks nullifiers
+        // against earlier claims, so repeats within the batch are rejected here
+        let nullifier = claim_input
+            .nullifier_scheme
+            .compute_nullifier(
+                &claim_input.user_address,
+                claim_input.nullifier_secret.as_ref(),
+                claim_input.epoch_id,
+            )
+            .expect("Missing nullifier secret");
+        let nullifier = compute_domain_nullifier(
+            &nullifier,
+            public_inputs.chain_id,
+            &public_inputs.verifying_contract,
+            &public_inputs.campaign_id,
+        );
+        assert!(nullifiers.insert(nullifier), "Duplicate nullifier in batch");
+
+        entries.push(BatchEntry {
+            nullifier,
+            recipient: *recipient,
+            amount: claim_input.amount,
+        });
+    }
+
+    // Step 5: Commit the shared fields once and one entry per claim
+    let output = BatchClaimOutput {
+        merkle_root: public_inputs.merkle_root,
+        epoch_id: public_inputs.epoch_id,
+        campaign_id: public_inputs.campaign_id,
+        ownership_verified: public_inputs.require_ownership,
+        hash_scheme: public_inputs.hash_scheme,
+        exclusion_root: public_inputs.exclusion_root.unwrap_or([0u8; 32]),
+        policy_commitment: public_inputs
+            .list_policy
+            .as_ref()
+            .map_or([0u8; 32], |policy| policy.commitment()),
+        chain_id: public_inputs.chain_id,
+        verifying_contract: public_inputs.verifying_contract,
+        entries,
+    };
+    env::commit_slice(&output.to_abi_bytes());
+}
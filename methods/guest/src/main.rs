@@ -1,47 +1,244 @@
 #![no_main]
 
+use core::allocation::{basket_digest, canonical_basket};
+use core::claim::{verify_exclusion, verify_list_policy, verify_membership, verify_ownership};
+use core::ownership::ClaimTerms;
+use core::vesting::{compute_tranche_nullifier, VESTING_EPOCH};
+use core::{
+    compute_domain_nullifier, root_set_commitment, ClaimInput, ClaimOutput, PublicInputs,
+    TierClaimOutput,
+};
 use risc0_zkvm::guest::env;
-use core::{ClaimInput, ClaimOutput, PublicInputs, compute_leaf, compute_nullifier, verify_merkle_proof};
 
 risc0_zkvm::guest::entry!(main);
 
 fn main() {
     // Read private inputs (user's claim data)
     let claim_input: ClaimInput = env::read();
-    
+
     // Read public inputs (expected root and epoch)
     let public_inputs: PublicInputs = env::read();
 
-    // Step 1: Compute the leaf hash from the user's address
-    let leaf = compute_leaf(&claim_input.user_address);
+    let scheme = claim_input.nullifier_scheme;
+    let secret = claim_input.nullifier_secret.as_ref();
 
-    // Step 2: Verify the Merkle proof
-    let is_valid = verify_merkle_proof(
-        &leaf,
-        &claim_input.merkle_proof,
-        claim_input.leaf_index,
-        &public_inputs.merkle_root,
-    );
+    // Pick the root to prove against; with a root set the claimer's choice
+    // stays private and only the set commitment is committed
+    let merkle_root = match &public_inputs.root_set {
+        None => public_inputs.merkle_root,
+        Some(roots) => {
+            assert_eq!(
+                root_set_commitment(roots),
+                public_inputs.merkle_root,
+                "Root set commitment mismatch"
+            );
+            let root_index = claim_input.root_index.expect("Missing root index");
+            *roots
+                .get(root_index as usize)
+                .expect("Root index out of range")
+        }
+    };
+
+    // Step 1-2: Compute the (address, amount) leaf and verify the Merkle proof with the tree's hasher
+    let is_valid = verify_membership(&claim_input, public_inputs.hash_scheme, &merkle_root);
 
     // Step 3: Assert the proof is valid
     assert!(is_valid, "Invalid Merkle proof");
 
+    // Step 3a: Prove the eligible address is not in the exclusion tree
+    if let Some(exclusion_root) = public_inputs.exclusion_root.as_ref() {
+        let proof = claim_input
+            .exclusion_proof
+            .as_ref()
+            .expect("Missing exclusion proof");
+        let not_excluded = verify_exclusion(
+            &claim_input.user_address,
+            proof,
+            public_inputs.hash_scheme,
+            exclusion_root,
+        );
+        assert!(not_excluded, "Address is excluded");
+    }
+
+    // Step 3b: Threshold campaigns prove the private balance meets the minimum
+    // and tiered campaigns prove which tier it falls in; only membership, the
+    // nullifier and the tier are committed, never the balance
+    let tier = match (public_inputs.min_balance, &public_inputs.tiers) {
+        (None, None) => {
+            assert!(
+                claim_input.balance.is_none(),
+                "Balance leaf without a threshold"
+            );
+            None
+        }
+        (min_balance, tiers) => {
+            assert!(
+                min_balance.is_none() || tiers.is_none(),
+                "The lowest tier replaces the threshold"
+            );
+            let balance = claim_input.balance.expect("Missing snapshot balance");
+            assert!(
+                claim_input.amount == 0
+                    && claim_input.vesting.is_none()
+                    && claim_input.basket.is_empty(),
+                "Threshold claims carry no allocation"
+            );
+            if let Some(min_balance) = min_balance {
+                assert!(balance >= min_balance, "Balance below threshold");
+            }
+            tiers.as_ref().map(|tiers| {
+                assert!(tiers.is_valid(), "Invalid tier table");
+                let tier = tiers
+                    .tier_of(balance)
+                    .expect("Balance below the lowest tier");
+                (tiers.digest(), tier)
+            })
+        }
+    };
+
+    // Step 3c: Prove membership in the additional lists the policy requires
+    if let Some(policy) = public_inputs.list_policy.as_ref() {
+        let is_satisfied = verify_list_policy(&claim_input, public_inputs.hash_scheme, policy);
+        assert!(is_satisfied, "List policy not satisfied");
+    }
+    let policy_commitment = public_inputs
+        .list_policy
+        .as_ref()
+        .map_or([0u8; 32], |policy| policy.commitment());
+
     // Step 4: Verify epoch matches
     assert_eq!(
         claim_input.epoch_id, public_inputs.epoch_id,
         "Epoch ID mismatch"
     );
 
-    // Step 5: Compute nullifier (prevents double-claiming)
-    let nullifier = compute_nullifier(&claim_input.user_address, claim_input.epoch_id);
+    // Step 4b: The validity window is committed and enforced where the claim is
+    // submitted; reject windows no clock could fall in
+    let not_before = public_inputs.not_before.unwrap_or(0);
+    if let Some(expires_at) = public_inputs.expires_at {
+        assert!(
+            expires_at > 0 && expires_at >= not_before,
+            "Empty validity window"
+        );
+    }
+    let expires_at = public_inputs.expires_at.unwrap_or(0);
+
+    // Step 5: Prove control of the eligible address when required
+    if public_inputs.require_ownership {
+        assert!(
+            claim_input.ownership_signature.is_some(),
+            "Missing ownership signature"
+        );
+        // The signature covers the relayer and fee, so nobody holding it can
+        // re-prove the claim with a larger cut for themselves
+        let terms = ClaimTerms::new(&public_inputs, &public_inputs.recipient);
+        assert!(
+            verify_ownership(&claim_input, &terms),
+            "Invalid ownership signature"
+        );
+    }
+
+    // Step 6: Compute nullifier (prevents double-claiming), bound to this
+    // deployment so the receipt cannot be replayed on another chain or contract
+    let domain_nullifier = |epoch_id| {
+        let nullifier = scheme
+            .compute_nullifier(&claim_input.user_address, secret, epoch_id)
+            .expect("Missing nullifier secret");
+        compute_domain_nullifier(
+            &nullifier,
+            public_inputs.chain_id,
+            &public_inputs.verifying_contract,
+            &public_inputs.campaign_id,
+        )
+    };
+    let nullifier = domain_nullifier(claim_input.epoch_id);
+
+    // Step 6b: Tiered claims commit only the tier; the contract pays per tier
+    if let Some((tier_table_digest, tier)) = tier {
+        assert_eq!(public_inputs.claimed_amount, 0, "Allocation does not vest");
+        assert_eq!(public_inputs.fee, 0, "Tier claims carry no relayer fee");
+        let output = TierClaimOutput {
+            merkle_root: public_inputs.merkle_root,
+            nullifier,
+            epoch_id: claim_input.epoch_id,
+            recipient: public_inputs.recipient,
+            campaign_id: public_inputs.campaign_id,
+            ownership_verified: public_inputs.require_ownership,
+            hash_scheme: public_inputs.hash_scheme,
+            tier_table_digest,
+            tier,
+            exclusion_root: public_inputs.exclusion_root.unwrap_or([0u8; 32]),
+            policy_commitment,
+            chain_id: public_inputs.chain_id,
+            verifying_contract: public_inputs.verifying_contract,
+            not_before,
+            expires_at,
+        };
+        env::commit_slice(&output.to_abi_bytes());
+        return;
+    }
+
+    // Step 7: Work out the payout; vesting claims pay the newly vested part
+    // under a tranche nullifier and open the next tranche
+    let (amount, nullifier, next_nullifier) = match &claim_input.vesting {
+        None => {
+            assert_eq!(public_inputs.claimed_amount, 0, "Allocation does not vest");
+            (claim_input.amount, nullifier, [0u8; 32])
+        }
+        Some(schedule) => {
+            // The basket is paid in full on every claim, so it cannot vest
+            assert!(
+                claim_input.basket.is_empty(),
+                "Vesting allocations cannot carry a basket"
+            );
+            let claimed_amount = public_inputs.claimed_amount;
+            let payout = schedule
+                .tranche_amount(claim_input.amount, public_inputs.timestamp, claimed_amount)
+                .expect("Nothing to claim");
+            // The tranche chain outlives the epoch, so a leaf republished by
+            // `setEpoch` continues its chain instead of paying out again
+            let chain = domain_nullifier(VESTING_EPOCH);
+            (
+                payout,
+                compute_tranche_nullifier(&chain, claimed_amount),
+                compute_tranche_nullifier(&chain, claimed_amount + payout),
+            )
+        }
+    };
+
+    // Step 7b: The relayer's cut comes out of the allocation; threshold
+    // payouts are fixed by the contract, which bounds the fee there
+    if public_inputs.min_balance.is_none() {
+        assert!(public_inputs.fee <= amount, "Fee exceeds the payout");
+    }
 
-    // Step 6: Create output to commit to journal
+    // Step 8: Create output to commit to journal
+    let basket = canonical_basket(&claim_input.basket).expect("Duplicate basket token");
     let output = ClaimOutput {
         merkle_root: public_inputs.merkle_root,
         nullifier,
         epoch_id: claim_input.epoch_id,
+        recipient: public_inputs.recipient,
+        campaign_id: public_inputs.campaign_id,
+        ownership_verified: public_inputs.require_ownership,
+        hash_scheme: public_inputs.hash_scheme,
+        amount,
+        basket_digest: basket_digest(&basket).expect("Duplicate basket token"),
+        timestamp: public_inputs.timestamp,
+        claimed_amount: public_inputs.claimed_amount,
+        next_nullifier,
+        min_balance: public_inputs.min_balance.unwrap_or(0),
+        exclusion_root: public_inputs.exclusion_root.unwrap_or([0u8; 32]),
+        policy_commitment,
+        relayer: public_inputs.relayer,
+        fee: public_inputs.fee,
+        chain_id: public_inputs.chain_id,
+        verifying_contract: public_inputs.verifying_contract,
+        not_before,
+        expires_at,
+        basket,
     };
 
-    // Step 7: Commit output to journal (makes it public)
-    env::commit(&output);
+    // Step 9: Commit the ABI-packed output to the journal (makes it public)
+    env::commit_slice(&output.to_abi_bytes());
 }